@@ -1,38 +1,74 @@
 mod asteroid;
+mod blueprint;
+mod checksum;
 pub mod collision_detection;
+mod delta;
+mod explosion;
 mod game_object;
+mod hull_class;
 pub mod material;
 pub mod player;
 mod projectile;
+mod query;
+pub mod replay;
 pub mod spacecraft;
 pub mod spacecraft_structure;
 mod star_base;
+mod targeting;
+mod wreck;
 
 use crate::prelude::*;
 pub use asteroid::Asteroid;
+pub use blueprint::{
+    Blueprint, BlueprintComponent, BlueprintError, BlueprintFile, BlueprintId, BLUEPRINT_VERSION,
+};
+pub use checksum::{stable_hash, Desync, GameObjectChecksum, StateChecksum};
+pub use delta::{BodyMotion, DeltaError, GameDelta, GameObjectChange, MAX_SNAPSHOTS};
+pub use explosion::{Explosion, Warhead};
 pub use game_object::*;
+pub use hull_class::HullClass;
 pub use material::Material;
 pub use player::{Player, PlayerId};
+pub use query::{OwnerFilter, QueryFilter, RaycastHit};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
 pub use spacecraft::Spacecraft;
-pub use spacecraft::{Component, ComponentCmd, ComponentId, ComponentType, Orientation};
+pub use spacecraft::{
+    CatalogueError, Component, ComponentCatalogue, ComponentCmd, ComponentId, ComponentType,
+    FlightGoal, Orientation,
+};
 pub use star_base::StarBase;
-pub use {projectile::Projectile, projectile::ProjectileType};
+use star_base::{DOCKING_RANGE, REFUEL_RATE};
+pub use targeting::{lead_intercept, TargetingMode};
+pub use wreck::Wreck;
+pub use {
+    projectile::Guidance, projectile::Projectile, projectile::ProjectileType, projectile::Target,
+};
 
 use std::cmp::Reverse;
 use std::collections::BinaryHeap;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
-use strum::IntoStaticStr;
+use strum::{EnumDiscriminants, IntoStaticStr};
 
-pub use spacecraft_structure::{ComponentPlaceholder, SpacecraftStructure};
+pub use spacecraft_structure::{ComponentPlaceholder, SpacecraftStructure, StructureError};
 
-use self::collision_detection::{check_sharp_collision, CollisionInfo};
+use self::collision_detection::{
+    approach_velocity, closest_point_on_polygon, closest_point_on_segment, ray_cast_polygon,
+    segment_intersects_polygon, time_of_impact, Aabb, BroadPhase, CollisionInfo, RESTING_SPEED,
+};
 
 pub const VERSION: &'static str = "0.0-release";
 
+/// Penetration depth tolerated before positions are corrected after a collision
+const PENETRATION_SLOP: f32 = 0.01;
+/// Fraction of the penetration depth removed by the positional correction
+const POSITION_CORRECTION: f32 = 0.8;
+/// Collisions resolved per game object and frame before the rest are dropped, bounds the work of
+/// contacts which keep being predicted again
+const MAX_COLLISIONS_PER_OBJECT: usize = 8;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct GameSync {
     pub last_update: Duration,
@@ -57,6 +93,8 @@ pub struct ExecutedGameCmd {
     pub user: User,
     pub cmd: GameCmd,
     pub time: Duration,
+    pub frame: usize,
+    pub time_elapsed: f32,
 }
 
 #[derive(Debug, Clone)]
@@ -64,20 +102,55 @@ pub enum GameEvent {
     ProjectileLaunched(Projectile),
     SpacecraftDeployed(Spacecraft),
     GameObjectDestroyed(GameObject, GameObject), // destroyed, destroyer
+    Explosion(Explosion),
+    BeamFired(Vec2, Vec2), // start, end
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(remote = "Self")]
 pub struct Game {
     pub sync: GameSync,
     pub players: HashMap<PlayerId, Player>, // public keys as public keys
     #[serde(skip)]
     pub cmds_history: Vec<ExecutedGameCmd>,
+    /// Duration of every update while a `ReplayRecorder` is attached, together with
+    /// `cmds_history` it allows replaying the game
+    #[serde(skip)]
+    pub dt_history: Vec<f32>,
+    /// Number of attached `ReplayRecorder`s
+    #[serde(skip)]
+    recorders: usize,
     pub game_objects: HashMap<GameObjectId, GameObject>,
     #[serde(skip)]
     pub events: Vec<GameEvent>,
+    /// Recent states saved with `save_snapshot`, used as baselines for `diff`
+    #[serde(skip)]
+    pub snapshots: VecDeque<Game>,
+    /// Bounding boxes of all game objects, refreshed every update and whenever objects are
+    /// spawned, deserialized or changed by a delta
+    #[serde(skip)]
+    pub broad_phase: BroadPhase,
     pub time_elapsed: f32,
     pub log: Vec<String>,
+    pub seed: Option<u64>,
     rng: ChaChaRng,
+    #[serde(default)]
+    pub component_catalogue: ComponentCatalogue,
+}
+
+impl Serialize for Game {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Game::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Game {
+    /// Rebuilds the broad phase, which isn't serialized, so spatial queries work right away
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut game = Game::deserialize(deserializer)?;
+        game.update_broad_phase();
+        Ok(game)
+    }
 }
 
 impl Game {
@@ -86,32 +159,237 @@ impl Game {
             sync: GameSync::new(),
             players: HashMap::new(),
             cmds_history: vec![],
+            dt_history: vec![],
+            recorders: 0,
             game_objects: HashMap::new(),
             events: vec![],
+            snapshots: VecDeque::new(),
+            broad_phase: BroadPhase::default(),
             time_elapsed: 0.,
             rng: ChaChaRng::from_entropy(),
             log: vec![],
+            seed: None,
+            component_catalogue: ComponentCatalogue::default(),
+        }
+    }
+
+    /// Creates a game whose simulation is fully determined by `seed` and the executed commands,
+    /// peers constructed with the same seed stay in lockstep when fed the same `GameCmd`s
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: ChaChaRng::seed_from_u64(seed),
+            seed: Some(seed),
+            ..Self::new()
+        }
+    }
+
+    /// Ids of all game objects in ascending order, iterate over these whenever the order
+    /// affects the simulation since `HashMap` iteration order differs between peers
+    pub fn sorted_game_object_ids(&self) -> Vec<GameObjectId> {
+        let mut ids = self.game_objects.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// Stable digest of players, game objects (ordered by id), elapsed time and rng state.
+    /// Two peers in lockstep produce the same hash for the same frame.
+    pub fn state_hash(&self) -> u64 {
+        stable_hash(&(
+            self.players.iter().collect::<BTreeMap<_, _>>(),
+            self.game_objects.iter().collect::<BTreeMap<_, _>>(),
+            self.time_elapsed,
+            &self.rng,
+            &self.component_catalogue,
+        ))
+    }
+
+    /// Detailed checksum of the current frame, compare it with `StateChecksum::diff` to find
+    /// out which players, objects or fields diverged
+    pub fn state_checksum(&self) -> StateChecksum {
+        StateChecksum::new(self)
+    }
+
+    /// Copy of the current state without any history, events or snapshots
+    pub fn snapshot(&self) -> Game {
+        Self {
+            sync: self.sync.clone(),
+            players: self.players.clone(),
+            cmds_history: vec![],
+            dt_history: vec![],
+            recorders: 0,
+            game_objects: self.game_objects.clone(),
+            events: vec![],
+            snapshots: VecDeque::new(),
+            broad_phase: BroadPhase::default(),
+            time_elapsed: self.time_elapsed,
+            log: self.log.clone(),
+            seed: self.seed,
+            rng: self.rng.clone(),
+            component_catalogue: self.component_catalogue.clone(),
+        }
+    }
+
+    /// Stores the current state as a baseline for `diff`, only the last `MAX_SNAPSHOTS` are kept
+    pub fn save_snapshot(&mut self) {
+        let snapshot = self.snapshot();
+        self.snapshots.push_back(snapshot);
+        while self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Delta from the saved snapshot of `baseline_frame` to the current state,
+    /// returns None if no such snapshot is kept anymore and a full sync is needed
+    pub fn diff(&self, baseline_frame: usize) -> Option<GameDelta> {
+        self.snapshots
+            .iter()
+            .find(|snapshot| snapshot.sync.frame == baseline_frame)
+            .map(|baseline| GameDelta::new(baseline, self))
+    }
+
+    pub fn apply_delta(&mut self, delta: GameDelta) -> Result<(), DeltaError> {
+        if self.sync.frame != delta.baseline_frame {
+            return Err(DeltaError::BaselineMismatch(
+                self.sync.frame,
+                delta.baseline_frame,
+            ));
         }
+        if let Some(id) = delta
+            .removed
+            .iter()
+            .chain(delta.changed.iter().map(|(id, _)| id))
+            .find(|id| !self.game_objects.contains_key(id))
+        {
+            return Err(DeltaError::InvalidId(*id));
+        }
+
+        for id in delta.removed {
+            self.game_objects.remove(&id);
+        }
+        for (id, changes) in delta.changed {
+            let game_object = self.game_objects.get_mut(&id).unwrap();
+            for change in changes {
+                change.apply(game_object);
+            }
+        }
+        self.game_objects.extend(delta.created);
+
+        if let Some(players) = delta.players {
+            self.players = players;
+        }
+        if let Some(component_catalogue) = delta.component_catalogue {
+            self.component_catalogue = component_catalogue;
+        }
+        self.log.extend(delta.log);
+        self.sync = delta.sync;
+        self.time_elapsed = delta.time_elapsed;
+        self.seed = delta.seed;
+        self.rng = delta.rng;
+        self.update_broad_phase();
+        Ok(())
     }
 
     pub fn update(&mut self, dt: f32) {
         self.sync.update();
         self.events = vec![];
+        if self.recorders > 0 {
+            self.dt_history.push(dt);
+        }
 
         self.time_elapsed += dt;
 
         self.update_collisions();
         self.update_game_objects();
+        self.update_broad_phase();
+        self.refuel_docked_spacecrafts(dt);
+    }
+
+    /// Star bases refuel the spacecrafts of their owner which are docked to them
+    fn refuel_docked_spacecrafts(&mut self, dt: f32) {
+        let mut docked = vec![];
+        for id in self.sorted_game_object_ids() {
+            let Some(GameObject::StarBase(star_base)) = self.game_objects.get(&id) else {
+                continue;
+            };
+            let mut nearby = self.objects_within_radius(star_base.body.position, DOCKING_RANGE);
+            nearby.sort();
+            for spacecraft_id in nearby {
+                if let Some(GameObject::Spacecraft(spacecraft)) =
+                    self.game_objects.get(&spacecraft_id)
+                {
+                    if star_base.docks(spacecraft) {
+                        docked.push(spacecraft_id);
+                    }
+                }
+            }
+        }
+        for id in docked {
+            if let Some(GameObject::Spacecraft(spacecraft)) = self.game_objects.get_mut(&id) {
+                spacecraft.refuel(REFUEL_RATE * dt);
+            }
+        }
+    }
+
+    /// Bounding box of the object's bounds in world coordinates. When `swept` is set the box also
+    /// covers the movement of the object until the end of the current frame.
+    pub fn game_object_aabb(&self, id: GameObjectId, swept: bool) -> Aabb {
+        let body = self.game_objects[&id].body();
+        let mut aabb = Aabb::from_points(body.world_bounds())
+            .unwrap_or(Aabb::new(body.position, body.position));
+
+        if swept && self.time_elapsed > body.cur_time {
+            let end = body.predicted(self.time_elapsed);
+            if body.angular_velocity != 0. {
+                // corners of a spinning body can reach anywhere within its radius
+                aabb = aabb
+                    .union(&Aabb::from_circle(body.position, body.radius()))
+                    .union(&Aabb::from_circle(end.position, body.radius()));
+            } else if let Some(end_aabb) = Aabb::from_points(end.world_bounds()) {
+                aabb = aabb.union(&end_aabb);
+            }
+        }
+
+        aabb
+    }
+
+    /// Brings the broad phase up to date with the current positions of all game objects
+    /// Adds the game object under a new id, it can be found by spatial queries right away
+    fn spawn(&mut self, game_object: GameObject) -> GameObjectId {
+        let id = self
+            .game_objects
+            .insert_with_unique_key(game_object, &mut self.rng);
+        let aabb = self.game_object_aabb(id, false);
+        self.broad_phase.update(id, aabb);
+        id
+    }
+
+    fn update_broad_phase(&mut self) {
+        self.broad_phase
+            .retain(|id| self.game_objects.contains_key(&id));
+        for id in self.sorted_game_object_ids() {
+            let aabb = self.game_object_aabb(id, false);
+            self.broad_phase.update(id, aabb);
+        }
     }
 
     fn update_game_objects(&mut self) {
-        let _ = self
+        let mut destroyed = self
             .game_objects
             .extract_if(|_, game_object| game_object.health() <= 0.)
             .collect::<Vec<_>>();
+        destroyed.sort_by_key(|(id, _)| *id);
 
         let mut effects = vec![];
-        for game_object in self.game_objects.values_mut() {
+        for (_, game_object) in destroyed {
+            if let GameObject::Asteroid(asteroid) = game_object {
+                effects.extend(asteroid.fracture(&mut self.rng));
+            }
+        }
+        self.update_guidance();
+        self.update_targeting();
+        effects.extend(self.check_proximity_fuses());
+        for id in self.sorted_game_object_ids() {
+            let game_object = self.game_objects.get_mut(&id).unwrap();
             effects.extend(game_object.update(self.time_elapsed));
         }
 
@@ -121,6 +399,156 @@ impl Game {
 
         // self.apply_gravity();
     }
+    /// Tells guided projectiles where their targets are, projectiles whose target is gone keep
+    /// flying to its last known position
+    fn update_guidance(&mut self) {
+        for id in self.sorted_game_object_ids() {
+            let Some(GameObject::Projectile(projectile)) = self.game_objects.get(&id) else {
+                continue;
+            };
+            let Some(Guidance {
+                target: Some(Target::GameObject(target_id)),
+                ..
+            }) = projectile.guidance
+            else {
+                continue;
+            };
+            let target_motion = self
+                .game_objects
+                .get(&target_id)
+                .map(|target| (target.body().position, target.body().velocity));
+
+            let Some(GameObject::Projectile(projectile)) = self.game_objects.get_mut(&id) else {
+                continue;
+            };
+            let Some(guidance) = &mut projectile.guidance else {
+                continue;
+            };
+            match target_motion {
+                Some((position, velocity)) => {
+                    guidance.target_position = Some(position);
+                    guidance.target_velocity = velocity;
+                }
+                None => {
+                    guidance.target = guidance.target_position.map(Target::Point);
+                    guidance.target_velocity = Vec2::ZERO;
+                }
+            }
+        }
+    }
+    /// Detonates explosive projectiles which got close to an object of another owner
+    fn check_proximity_fuses(&mut self) -> Vec<GameObjectEffect> {
+        let mut effects = vec![];
+        for id in self.sorted_game_object_ids() {
+            let Some(GameObject::Projectile(projectile)) = self.game_objects.get(&id) else {
+                continue;
+            };
+            let Some(warhead) = projectile.warhead.filter(|warhead| warhead.proximity > 0.) else {
+                continue;
+            };
+            let position = projectile.body.position;
+            let owner = projectile.owner();
+            let triggered = self
+                .objects_within_radius(position, warhead.proximity)
+                .into_iter()
+                .filter_map(|other_id| self.game_objects.get(&other_id))
+                .filter(|other| {
+                    !matches!(other, GameObject::Projectile(_))
+                        && other.owner() != owner
+                        && other.health() > 0.
+                })
+                .any(|other| {
+                    closest_point_on_polygon(position, &other.body().world_bounds())
+                        .is_some_and(|point| point.distance(position) <= warhead.proximity)
+                });
+            if !triggered {
+                continue;
+            }
+            if let Some(GameObject::Projectile(projectile)) = self.game_objects.get_mut(&id) {
+                effects.extend(projectile.detonate().map(GameObjectEffect::Explosion));
+            }
+        }
+        effects
+    }
+    /// Damages and pushes away every object within the blast radius, the blast is weakened
+    /// by the distance and by every object standing between the center and the hit object
+    fn explode(&mut self, explosion: Explosion) {
+        self.events.push(GameEvent::Explosion(explosion.clone()));
+
+        let mut ids = self.objects_within_radius(explosion.position, explosion.radius);
+        ids.sort();
+        ids.retain(|id| self.game_objects[id].health() > 0.);
+        let bounds = ids
+            .iter()
+            .map(|id| self.game_objects[id].body().world_bounds())
+            .collect::<Vec<_>>();
+
+        let mut hits = vec![];
+        for (i, id) in ids.iter().enumerate() {
+            let Some(point) = closest_point_on_polygon(explosion.position, &bounds[i]) else {
+                continue;
+            };
+            if point.distance(explosion.position) > explosion.radius {
+                continue;
+            }
+            let occluders = bounds
+                .iter()
+                .enumerate()
+                .filter(|(j, other_bounds)| {
+                    *j != i && segment_intersects_polygon(explosion.position, point, other_bounds)
+                })
+                .count();
+            hits.push((*id, point, explosion.attenuation(point, occluders)));
+        }
+
+        for (id, point, attenuation) in hits {
+            let game_object = self.game_objects.get_mut(&id).unwrap();
+            let mass = game_object.mass();
+            let inertia = game_object.inertia().max(f32::EPSILON);
+            let body = game_object.body_mut();
+            let direction = (point - explosion.position)
+                .try_normalize()
+                .unwrap_or((body.position - explosion.position).normalize_or_zero());
+            let impulse = direction * explosion.impulse * attenuation;
+            body.velocity += impulse / mass;
+            body.angular_velocity += (point - body.position).perp_dot(impulse) / inertia;
+            // invalidates the collisions predicted with the old velocity
+            body.updated += 1;
+
+            let material_gain = game_object.apply_damage(explosion.damage * attenuation, point);
+            if let Some(player) = explosion
+                .owner
+                .and_then(|owner| self.players.get_mut(&owner))
+            {
+                player.give_materials(material_gain);
+            }
+        }
+    }
+    /// Damages the first object of another owner along the beam
+    fn fire_beam(
+        &mut self,
+        origin: Vec2,
+        direction: Vec2,
+        range: f32,
+        damage: f32,
+        owner: PlayerId,
+    ) {
+        let filter = QueryFilter::default().with_owner(OwnerFilter::Except(owner));
+        let Some(hit) = self.raycast(origin, direction, range, &filter) else {
+            self.events
+                .push(GameEvent::BeamFired(origin, origin + direction * range));
+            return;
+        };
+        self.events.push(GameEvent::BeamFired(origin, hit.point));
+        let material_gain = self
+            .game_objects
+            .get_mut(&hit.id)
+            .unwrap()
+            .apply_damage(damage, hit.point);
+        if let Some(player) = self.players.get_mut(&owner) {
+            player.give_materials(material_gain);
+        }
+    }
     pub fn apply_gravity(&mut self) {
         let mut asteroids = self
             .game_objects
@@ -150,6 +578,8 @@ impl Game {
             user,
             cmd: cmd.clone(),
             time: now(),
+            frame: self.sync.frame,
+            time_elapsed: self.time_elapsed,
         });
         match cmd {
             GameCmd::GiveMaterials(player_id, materials) => {
@@ -162,6 +592,15 @@ impl Game {
 
                 player.give_materials(materials.into_iter().collect());
             }
+            GameCmd::SetComponentCatalogue(component_catalogue) => {
+                if user != User::Server {
+                    return Err(GameCmdExecutionError::NotAuthorized);
+                }
+                component_catalogue
+                    .validate()
+                    .map_err(GameCmdExecutionError::InvalidCatalogue)?;
+                self.component_catalogue = component_catalogue;
+            }
             GameCmd::AddLogMessage(msg) => {
                 if user != User::Server {
                     return Err(GameCmdExecutionError::NotAuthorized);
@@ -175,15 +614,12 @@ impl Game {
                 if !self.players.contains_key(&player_id) {
                     return Err(GameCmdExecutionError::InvalidId);
                 }
-                self.game_objects.insert_with_unique_key(
-                    GameObject::StarBase(StarBase::new(
-                        position,
-                        velocity,
-                        self.time_elapsed,
-                        player_id,
-                    )),
-                    &mut self.rng,
-                );
+                self.spawn(GameObject::StarBase(StarBase::new(
+                    position,
+                    velocity,
+                    self.time_elapsed,
+                    player_id,
+                )));
             }
             GameCmd::SpawnRandomAsteroid(pos, vel) => {
                 if user != User::Server {
@@ -195,44 +631,47 @@ impl Game {
                     self.time_elapsed,
                     self.rng.gen::<f32>().powi(2) * 100. + 2.,
                     self.rng.gen(),
+                    &mut self.rng,
                 );
-                self.game_objects
-                    .insert_with_unique_key(GameObject::Asteroid(new_asteroid), &mut self.rng);
+                self.spawn(GameObject::Asteroid(new_asteroid));
             }
             GameCmd::BuildSpacecraft(game_object_id, spacecraft_structure, hangar_index) => {
-                let Some(GameObject::StarBase(star_base)) =
-                    self.game_objects.get_mut(&game_object_id)
+                self.build_spacecraft(user, game_object_id, &spacecraft_structure, hangar_index)?;
+            }
+            GameCmd::BuildBlueprint(game_object_id, blueprint_id, hangar_index) => {
+                let Some(GameObject::StarBase(star_base)) = self.game_objects.get(&game_object_id)
                 else {
                     return Err(GameCmdExecutionError::InvalidId);
                 };
-                match user {
-                    User::Player(player_id) => {
-                        if star_base.owner != player_id {
-                            return Err(GameCmdExecutionError::NotAuthorized);
-                        }
-                    }
-                    User::Spectator => {
-                        return Err(GameCmdExecutionError::NotAuthorized);
-                    }
-                    User::Server => (),
+                let Some(blueprint) = self
+                    .players
+                    .get(&star_base.owner)
+                    .and_then(|player| player.blueprints.get(&blueprint_id))
+                else {
+                    return Err(GameCmdExecutionError::InvalidId);
+                };
+                let structure = blueprint.structure.clone();
+                self.build_spacecraft(user, game_object_id, &structure, hangar_index)?;
+            }
+            GameCmd::SaveBlueprint(player_id, name, structure) => {
+                self.controlled_player(user, player_id)?
+                    .save_blueprint(Blueprint::new(name, structure));
+            }
+            GameCmd::RenameBlueprint(player_id, blueprint_id, name) => {
+                if !self
+                    .controlled_player(user, player_id)?
+                    .rename_blueprint(blueprint_id, name)
+                {
+                    return Err(GameCmdExecutionError::InvalidId);
                 }
-
-                let player_id = star_base.owner;
-
-                let materials_required = &spacecraft_structure.materials();
-                if spacecraft_structure.valid()
-                    && star_base.can_build_spacecraft(&spacecraft_structure, hangar_index)
-                    && self
-                        .players
-                        .get_mut(&player_id)
-                        .unwrap()
-                        .take_materials(materials_required)
+            }
+            GameCmd::DeleteBlueprint(player_id, blueprint_id) => {
+                if self
+                    .controlled_player(user, player_id)?
+                    .delete_blueprint(blueprint_id)
+                    .is_none()
                 {
-                    star_base.build_spacecraft(&spacecraft_structure, hangar_index)
-                } else {
-                    return Err(GameCmdExecutionError::Other(
-                        "Couldn't build spacecraft".to_string(),
-                    ));
+                    return Err(GameCmdExecutionError::InvalidId);
                 }
             }
             GameCmd::DeploySpacecraft(game_object_id, hangar_index) => {
@@ -257,24 +696,16 @@ impl Game {
                 star_base.deploy_spacecraft(hangar_index);
             }
             GameCmd::ExecuteComponentCmd(game_object_id, component_id, component_cmd) => {
-                let Some(GameObject::Spacecraft(spacecraft)) =
-                    self.game_objects.get_mut(&game_object_id)
-                else {
-                    return Err(GameCmdExecutionError::InvalidId);
-                };
-                match user {
-                    User::Player(player_id) => {
-                        if spacecraft.owner != player_id {
-                            return Err(GameCmdExecutionError::NotAuthorized);
-                        }
-                    }
-                    User::Spectator => {
-                        return Err(GameCmdExecutionError::NotAuthorized);
-                    }
-                    User::Server => (),
-                }
-
-                spacecraft.execute_component_cmd(component_id, component_cmd);
+                self.controlled_spacecraft(user, game_object_id)?
+                    .execute_component_cmd(component_id, component_cmd);
+            }
+            GameCmd::SetFlightGoal(game_object_id, goal) => {
+                self.controlled_spacecraft(user, game_object_id)?
+                    .set_flight_goal(goal);
+            }
+            GameCmd::DisengageFlightController(game_object_id) => {
+                self.controlled_spacecraft(user, game_object_id)?
+                    .disengage_flight_controller();
             }
             GameCmd::AddPlayer(player_id) => {
                 if user != User::Server {
@@ -295,19 +726,122 @@ impl Game {
         Ok(())
     }
 
+    /// Validates the structure against the hull class and the hangar of the user's star base, then
+    /// pays for it and queues it for building
+    fn build_spacecraft(
+        &mut self,
+        user: User,
+        game_object_id: GameObjectId,
+        structure: &SpacecraftStructure,
+        hangar_index: usize,
+    ) -> Result<(), GameCmdExecutionError> {
+        let Some(GameObject::StarBase(star_base)) = self.game_objects.get_mut(&game_object_id)
+        else {
+            return Err(GameCmdExecutionError::InvalidId);
+        };
+        match user {
+            User::Player(player_id) => {
+                if star_base.owner != player_id {
+                    return Err(GameCmdExecutionError::NotAuthorized);
+                }
+            }
+            User::Spectator => {
+                return Err(GameCmdExecutionError::NotAuthorized);
+            }
+            User::Server => (),
+        }
+
+        let player_id = star_base.owner;
+
+        let catalogue = &self.component_catalogue;
+        let structure_errors = structure.validate(catalogue);
+        if !structure_errors.is_empty() {
+            return Err(GameCmdExecutionError::InvalidStructure(structure_errors));
+        }
+        if !star_base.can_build_spacecraft(structure, hangar_index, catalogue) {
+            return Err(GameCmdExecutionError::Other(
+                "Hangar missing or too small".to_string(),
+            ));
+        }
+        let materials_required = &structure.materials(catalogue);
+        if !self
+            .players
+            .get_mut(&player_id)
+            .unwrap()
+            .take_materials(materials_required)
+        {
+            return Err(GameCmdExecutionError::Other(
+                "Not enough materials".to_string(),
+            ));
+        }
+        star_base.build_spacecraft(structure, hangar_index, catalogue);
+        Ok(())
+    }
+    fn controlled_player(
+        &mut self,
+        user: User,
+        player_id: PlayerId,
+    ) -> Result<&mut Player, GameCmdExecutionError> {
+        match user {
+            User::Player(user_id) if user_id == player_id => (),
+            User::Server => (),
+            _ => return Err(GameCmdExecutionError::NotAuthorized),
+        }
+        self.players
+            .get_mut(&player_id)
+            .ok_or(GameCmdExecutionError::InvalidId)
+    }
+    /// Spacecraft the user is allowed to control
+    fn controlled_spacecraft(
+        &mut self,
+        user: User,
+        game_object_id: GameObjectId,
+    ) -> Result<&mut Spacecraft, GameCmdExecutionError> {
+        let Some(GameObject::Spacecraft(spacecraft)) = self.game_objects.get_mut(&game_object_id)
+        else {
+            return Err(GameCmdExecutionError::InvalidId);
+        };
+        match user {
+            User::Player(player_id) => {
+                if spacecraft.owner != player_id {
+                    return Err(GameCmdExecutionError::NotAuthorized);
+                }
+            }
+            User::Spectator => {
+                return Err(GameCmdExecutionError::NotAuthorized);
+            }
+            User::Server => (),
+        }
+        Ok(spacecraft)
+    }
     fn handle_game_object_effect(&mut self, effect: GameObjectEffect) {
         match effect {
             GameObjectEffect::LaunchProjectile(projectile) => {
                 self.events
                     .push(GameEvent::ProjectileLaunched(projectile.clone()));
-                self.game_objects
-                    .insert_with_unique_key(GameObject::Projectile(projectile), &mut self.rng);
+                self.spawn(GameObject::Projectile(projectile));
             }
             GameObjectEffect::SpawnSpacecraft(spacecraft) => {
                 self.events
                     .push(GameEvent::SpacecraftDeployed(spacecraft.clone()));
-                self.game_objects
-                    .insert_with_unique_key(GameObject::Spacecraft(spacecraft), &mut self.rng);
+                self.spawn(GameObject::Spacecraft(spacecraft));
+            }
+            GameObjectEffect::BuildSpacecraft(structure, owner, transform) => {
+                let spacecraft =
+                    Spacecraft::build(structure, owner, transform, &self.component_catalogue);
+                self.handle_game_object_effect(GameObjectEffect::SpawnSpacecraft(spacecraft));
+            }
+            GameObjectEffect::Explosion(explosion) => {
+                self.explode(explosion);
+            }
+            GameObjectEffect::FireBeam(origin, direction, range, damage, owner) => {
+                self.fire_beam(origin, direction, range, damage, owner);
+            }
+            GameObjectEffect::SpawnAsteroid(asteroid) => {
+                self.spawn(GameObject::Asteroid(asteroid));
+            }
+            GameObjectEffect::SpawnWreck(wreck) => {
+                self.spawn(GameObject::Wreck(wreck));
             }
         }
     }
@@ -315,7 +849,7 @@ impl Game {
     fn update_collisions(&mut self) {
         let mut destroyed_game_objects: Vec<(GameObjectId, GameObjectId)> = vec![];
 
-        let game_object_ids = self.game_objects.keys().copied().collect::<Vec<_>>();
+        let game_object_ids = self.sorted_game_object_ids();
 
         // -------------------COLLISIONS------------------- //
 
@@ -338,108 +872,39 @@ impl Game {
             };
         }
 
-        //---------------- x-bound check optimization --------------------
+        //---------------- broad phase --------------------
 
-        #[derive(PartialOrd, PartialEq, Copy, Clone, Default)]
-        struct XBound(f32, f32, GameObjectId);
-        impl Eq for XBound {}
-        impl Ord for XBound {
-            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-                self.0.partial_cmp(&other.0).unwrap()
-            }
+        self.broad_phase
+            .retain(|id| self.game_objects.contains_key(&id));
+        for id in &game_object_ids {
+            let aabb = self.game_object_aabb(*id, true);
+            self.broad_phase.update(*id, aabb);
         }
 
-        macro_rules! compute_x_bound {
-            ($id: expr) => {{
-                let mut body = self.game_objects[$id].body().clone();
-                let mut xs = body
-                    .bounds
-                    .clone()
-                    .into_iter()
-                    .map(|p| body.relative_to_world(p).x)
-                    .collect::<Vec<_>>();
-
-                body.update_fixed(self.time_elapsed);
-
-                xs.extend(
-                    body.bounds
-                        .clone()
-                        .into_iter()
-                        .map(|p| body.relative_to_world(p).x),
-                );
-
-                (
-                    *xs.iter()
-                        .min_by(|a, b| a.partial_cmp(b).unwrap())
-                        .unwrap_or(&-1.),
-                    *xs.iter()
-                        .max_by(|a, b| a.partial_cmp(b).unwrap())
-                        .unwrap_or(&0.),
-                )
-            }};
+        for (id, other_id) in self.broad_phase.pairs() {
+            add_collisions!(id, other_id);
         }
 
-        let mut x_bounds = game_object_ids
-            .iter()
-            .map(|id| {
-                let (min, max) = compute_x_bound!(id);
-                XBound(min, max, *id)
-            })
-            .collect::<Vec<_>>();
-
-        let mut bounds_left_bt = BTreeSet::from_iter(x_bounds.clone());
-        let mut bounds_right_bt = BTreeSet::from_iter(
-            x_bounds
-                .iter()
-                .map(|bound| XBound(bound.1, bound.0, bound.2)),
-        );
-
-        let id_map = game_object_ids
-            .iter()
-            .enumerate()
-            .map(|(i, id)| (*id, i))
-            .collect::<HashMap<_, _>>();
-
-        game_object_ids.iter().enumerate().for_each(|(i, id)| {
-            let x_range = x_bounds[i]..XBound(x_bounds[i].1, 0., 0);
-
-            for other_bound in bounds_left_bt
-                .range(x_range.clone())
-                .chain(bounds_right_bt.range(x_range))
-            {
-                add_collisions!(*id, other_bound.2);
-            }
-        });
-
+        let mut collisions_left = game_object_ids.len() * MAX_COLLISIONS_PER_OBJECT;
         while let Some(Reverse(col)) = collisions_pq.pop() {
+            if collisions_left == 0 {
+                break;
+            }
             if !self.handle_collision(col) {
                 continue;
             }
+            collisions_left -= 1;
 
             let ids = [col.sharp_obj.0, col.other_obj.0];
             for i in 0..2 {
                 if self.game_objects[&ids[i]].health() <= 0. {
                     destroyed_game_objects.push((ids[i], ids[(i + 1) % 2]));
                 }
-                let new_bound = compute_x_bound!(&ids[i]);
-                let new_bound = XBound(new_bound.0, new_bound.1, ids[i]);
-
-                let old_bound = x_bounds[id_map[&ids[i]]];
-                x_bounds[id_map[&ids[i]]] = new_bound;
-
-                bounds_left_bt.remove(&old_bound);
-                bounds_right_bt.remove(&XBound(old_bound.1, old_bound.0, old_bound.2));
+                let aabb = self.game_object_aabb(ids[i], true);
+                self.broad_phase.update(ids[i], aabb);
 
-                bounds_left_bt.insert(new_bound);
-                bounds_right_bt.insert(XBound(new_bound.1, new_bound.0, new_bound.2));
-
-                let x_range = new_bound..XBound(new_bound.1, 0., 0);
-
-                for other_bound in bounds_left_bt
-                    .range(x_range.clone())
-                    .chain(bounds_right_bt.range(x_range))
-                {
-                    add_collisions!(ids[i], other_bound.2);
+                for other_id in self.broad_phase.candidates(ids[i]) {
+                    add_collisions!(ids[i], other_id);
                 }
             }
         }
@@ -498,30 +963,77 @@ impl Game {
                 .point_position((other_obj_line + 1) % other_obj.body().bounds.len()),
         );
         let point_of_collision = sharp_obj.body().point_position(sharp_obj_point);
+        let point_on_edge = closest_point_on_segment(point_of_collision, col_line.0, col_line.1);
 
-        let normal = (col_line.0 - col_line.1).perp().normalize();
+        // points out of the other object towards the sharp one
+        let mut normal = (col_line.0 - col_line.1).perp().normalize_or_zero();
+        if normal.dot(point_on_edge - other_obj.body().position) < 0. {
+            normal = -normal;
+        }
+        let tangent = normal.perp();
 
-        let mass1 = sharp_obj.mass();
-        let mass2 = other_obj.mass();
+        let inv_mass1 = 1. / sharp_obj.mass();
+        let inv_mass2 = 1. / other_obj.mass();
+        let inv_inertia1 = 1. / sharp_obj.inertia().max(f32::EPSILON);
+        let inv_inertia2 = 1. / other_obj.inertia().max(f32::EPSILON);
+        let arm1 = point_of_collision - sharp_obj.body().position;
+        let arm2 = point_of_collision - other_obj.body().position;
 
         let bounciness = sharp_obj.bounciness() * other_obj.bounciness();
+        let friction = (sharp_obj.friction() * other_obj.friction()).sqrt();
+
+        let relative_velocity = sharp_obj.body().point_velocity(point_of_collision)
+            - other_obj.body().point_velocity(point_of_collision);
+        // the same definition `time_of_impact` predicts contacts with
+        let normal_velocity = approach_velocity(
+            sharp_obj.body(),
+            other_obj.body(),
+            point_of_collision,
+            normal,
+        );
+        let penetration = (point_on_edge - point_of_collision).dot(normal);
 
-        let relative_velocity = other_obj.body().velocity - sharp_obj.body().velocity;
+        // separating or already resolved, handling it would only predict it again
+        if normal_velocity >= -RESTING_SPEED && penetration <= PENETRATION_SLOP {
+            return false;
+        }
 
-        let impulse_numerator = -(1. + bounciness) * (relative_velocity).dot(normal);
-        let impulse_denominator = (1. / mass1) + (1. / mass2);
-        let impulse = impulse_numerator / impulse_denominator;
+        // effective inverse mass of the contact along a direction, including rotation
+        let inv_effective_mass = |dir: Vec2| {
+            inv_mass1
+                + inv_mass2
+                + arm1.perp_dot(dir).powi(2) * inv_inertia1
+                + arm2.perp_dot(dir).powi(2) * inv_inertia2
+        };
+
+        let (impulse, friction_impulse) = if normal_velocity < -RESTING_SPEED {
+            let impulse = -(1. + bounciness) * normal_velocity / inv_effective_mass(normal);
+            let friction_impulse = (-relative_velocity.dot(tangent) / inv_effective_mass(tangent))
+                .clamp(-friction * impulse, friction * impulse);
+            (impulse, friction_impulse)
+        } else {
+            (0., 0.)
+        };
+        let total_impulse = normal * impulse + tangent * friction_impulse;
+
+        let correction = (penetration - PENETRATION_SLOP).max(0.) * POSITION_CORRECTION
+            / (inv_mass1 + inv_mass2);
 
         let destructive_power = sharp_obj.destructive_power() * other_obj.destructive_power();
-        let damage =
-            (1. - bounciness) * relative_velocity.dot(normal).abs().powi(2) * destructive_power;
+        let damage = (1. - bounciness) * normal_velocity.min(0.).powi(2) * destructive_power;
+        let mass1 = sharp_obj.mass();
+        let mass2 = other_obj.mass();
 
         let sharp_obj_owner = sharp_obj.owner();
         let other_obj_owner = other_obj.owner();
 
         let sharp_obj = self.game_objects.get_mut(&sharp_obj_id).unwrap();
-        sharp_obj.body_mut().velocity -= impulse * normal / mass1;
-        sharp_obj.body_mut().position += normal * 0.005;
+        let body = sharp_obj.body_mut();
+        body.velocity += total_impulse * inv_mass1;
+        body.angular_velocity += arm1.perp_dot(total_impulse) * inv_inertia1;
+        body.position += normal * correction * inv_mass1;
+        // the collisions predicted with the old motion are stale
+        body.updated += 1;
 
         let material_gain = sharp_obj.apply_damage(damage * mass2, point_of_collision);
         if let Some(player_id) = other_obj_owner {
@@ -530,9 +1042,11 @@ impl Game {
         }
 
         let other_obj = self.game_objects.get_mut(&other_obj_id).unwrap();
-
-        other_obj.body_mut().velocity += impulse * normal / mass2;
-        other_obj.body_mut().position -= normal * 0.005;
+        let body = other_obj.body_mut();
+        body.velocity -= total_impulse * inv_mass2;
+        body.angular_velocity -= arm2.perp_dot(total_impulse) * inv_inertia2;
+        body.position -= normal * correction * inv_mass2;
+        body.updated += 1;
 
         let material_gain = other_obj.apply_damage(damage * mass1, point_of_collision);
         if let Some(player_id) = sharp_obj_owner {
@@ -540,6 +1054,17 @@ impl Game {
             player.give_materials(material_gain);
         }
 
+        // explosive projectiles detonate on contact
+        for id in [sharp_obj_id, other_obj_id] {
+            if let Some(GameObject::Projectile(projectile)) = self.game_objects.get_mut(&id) {
+                if projectile.warhead.is_some() {
+                    if let Some(explosion) = projectile.detonate() {
+                        self.explode(explosion);
+                    }
+                }
+            }
+        }
+
         true
     }
 
@@ -548,46 +1073,33 @@ impl Game {
         sharp_obj_id: GameObjectId,
         other_obj_id: GameObjectId,
     ) -> Option<CollisionInfo> {
-        let mut sharp_body = self.game_objects[&sharp_obj_id].body().clone();
-        let mut other_body = self.game_objects[&other_obj_id].body().clone();
+        let sharp_body = self.game_objects[&sharp_obj_id].body();
+        let other_body = self.game_objects[&other_obj_id].body();
 
         let cur_time = sharp_body.cur_time.max(other_body.cur_time);
 
-        sharp_body.position += sharp_body.velocity * (cur_time - sharp_body.cur_time);
-        other_body.position += other_body.velocity * (cur_time - other_body.cur_time);
-
-        sharp_body.velocity -= other_body.velocity;
-        other_body.velocity = Vec2::ZERO;
-
-        // we rotate extra 1 (any number) to avoid vertical and horizontal lines
-        let extra_rot = 1.;
-        let sharp_points = sharp_body
-            .bounds
-            .clone()
-            .into_iter()
-            .map(|x| sharp_body.relative_to_world(x).rotate_rad(extra_rot))
-            .collect();
-        let other_points = other_body
-            .bounds
-            .clone()
-            .into_iter()
-            .map(|x| other_body.relative_to_world(x).rotate_rad(extra_rot))
-            .collect();
-        sharp_body.velocity = sharp_body.velocity.rotate_rad(extra_rot);
-
-        check_sharp_collision(
-            sharp_points,
-            other_points,
-            sharp_body.velocity,
+        time_of_impact(
+            &sharp_body.predicted(cur_time),
+            &other_body.predicted(cur_time),
             self.time_elapsed - cur_time,
         )
-        .map(|(dt, sharp_point, other_line)| CollisionInfo {
-            time: cur_time + dt,
-            sharp_obj: (sharp_obj_id, sharp_body.updated, sharp_point),
-            other_obj: (other_obj_id, other_body.updated, other_line),
+        .map(|contact| CollisionInfo {
+            time: cur_time + contact.time,
+            sharp_obj: (sharp_obj_id, sharp_body.updated, contact.sharp_point),
+            other_obj: (other_obj_id, other_body.updated, contact.other_line),
         })
     }
 
+    /// Game objects whose bounding box overlaps `area`
+    pub fn objects_in_rect(&self, area: &Aabb) -> Vec<GameObjectId> {
+        self.broad_phase.query_aabb(area)
+    }
+
+    /// Game objects whose bounding box is at most `radius` away from `center`
+    pub fn objects_within_radius(&self, center: Vec2, radius: f32) -> Vec<GameObjectId> {
+        self.broad_phase.query_radius(center, radius)
+    }
+
     pub fn spacecrafts(&self) -> Vec<&Spacecraft> {
         self.game_objects
             .values()
@@ -627,6 +1139,19 @@ impl Game {
             .collect()
     }
 
+    pub fn wrecks(&self) -> Vec<&Wreck> {
+        self.game_objects
+            .values()
+            .filter_map(|game_object| {
+                if let GameObject::Wreck(wreck) = game_object {
+                    Some(wreck)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
     pub fn projectiles(&self) -> Vec<&Projectile> {
         self.game_objects
             .values()
@@ -653,6 +1178,16 @@ pub enum GameCmd {
     SpawnStarBase(PlayerId, Vec2, Vec2),
     AddLogMessage(String),
     GiveMaterials(PlayerId, BTreeMap<Material, f32>),
+    SetComponentCatalogue(ComponentCatalogue),
+    /// Lets the flight controller of the spacecraft fly it to the goal
+    SetFlightGoal(GameObjectId, FlightGoal),
+    /// Stops the flight controller and shuts down the engines of the spacecraft
+    DisengageFlightController(GameObjectId),
+    /// Builds the blueprint from the library of the owner of the star base
+    BuildBlueprint(GameObjectId, BlueprintId, usize),
+    SaveBlueprint(PlayerId, String, SpacecraftStructure),
+    RenameBlueprint(PlayerId, BlueprintId, String),
+    DeleteBlueprint(PlayerId, BlueprintId),
 }
 
 pub fn run_game(game: Arc<RwLock<Game>>, tick_rate: u32) {
@@ -679,5 +1214,132 @@ pub enum User {
 pub enum GameCmdExecutionError {
     NotAuthorized,
     InvalidId,
+    InvalidCatalogue(CatalogueError),
+    InvalidStructure(Vec<StructureError>),
     Other(String),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_and_commands_stay_in_lockstep() {
+        let run = |seed| {
+            let mut game = Game::with_seed(seed);
+            game.execute_cmd(User::Server, GameCmd::AddPlayer(1))
+                .unwrap();
+            for i in 0..20 {
+                let cmd =
+                    GameCmd::SpawnRandomAsteroid(vec2(i as f32 * 30., 0.), vec2(0., i as f32));
+                game.execute_cmd(User::Server, cmd).unwrap();
+            }
+            let cmd = GameCmd::SpawnStarBase(1, vec2(0., 200.), Vec2::ZERO);
+            game.execute_cmd(User::Server, cmd).unwrap();
+            (0..100)
+                .map(|_| {
+                    game.update(0.05);
+                    game.state_hash()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).last(), run(8).last());
+    }
+
+    #[test]
+    fn asteroids_come_to_rest_on_a_star_base() {
+        let mut game = Game::with_seed(5);
+        game.execute_cmd(User::Server, GameCmd::AddPlayer(1))
+            .unwrap();
+        game.execute_cmd(
+            User::Server,
+            GameCmd::SpawnStarBase(1, Vec2::ZERO, Vec2::ZERO),
+        )
+        .unwrap();
+        for i in 0..30 {
+            let cmd = GameCmd::SpawnRandomAsteroid(vec2(i as f32 * 7. - 100., 40.), vec2(0., -3.));
+            game.execute_cmd(User::Server, cmd).unwrap();
+        }
+        for _ in 0..300 {
+            game.update(0.05);
+        }
+        // none of them sank into the star base
+        let star_base = game.star_bases()[0].body.world_bounds();
+        for asteroid in game.asteroids() {
+            assert!(!collision_detection::point_in_convex_polygon(
+                asteroid.body.position,
+                &star_base
+            ));
+        }
+    }
+
+    /// Unit asteroid with the given bounds, not rotated
+    fn add_asteroid(
+        game: &mut Game,
+        position: Vec2,
+        velocity: Vec2,
+        bounds: Vec<Vec2>,
+    ) -> GameObjectId {
+        let mut asteroid = Asteroid::new(position, velocity, 0., 1., Material::Iron, &mut game.rng);
+        asteroid.body = GameObjectBody::new(position, velocity, 0., 0., bounds);
+        game.game_objects
+            .insert_with_unique_key(GameObject::Asteroid(asteroid), &mut game.rng)
+    }
+
+    /// A diamond whose lowest corner hits the top edge of a resting square right of its center
+    fn corner_hit(height: f32, velocity: Vec2) -> (Game, CollisionInfo) {
+        let mut game = Game::with_seed(0);
+        let square = vec![vec2(-1., -1.), vec2(1., -1.), vec2(1., 1.), vec2(-1., 1.)];
+        let diamond = vec![vec2(0., -1.), vec2(1., 0.), vec2(0., 1.), vec2(-1., 0.)];
+        let other = add_asteroid(&mut game, Vec2::ZERO, Vec2::ZERO, square);
+        let sharp = add_asteroid(&mut game, vec2(0.5, height), velocity, diamond);
+        let collision = CollisionInfo {
+            time: 0.,
+            sharp_obj: (sharp, game.game_objects[&sharp].body().updated, 0),
+            other_obj: (other, game.game_objects[&other].body().updated, 2),
+        };
+        (game, collision)
+    }
+
+    #[test]
+    fn off_centre_hit_spins_and_conserves_momentum() {
+        let (mut game, collision) = corner_hit(2., vec2(2., -1.));
+        let (sharp, other) = (collision.sharp_obj.0, collision.other_obj.0);
+        let momentum = |game: &Game, id| {
+            let game_object = &game.game_objects[&id];
+            game_object.body().velocity * game_object.mass()
+        };
+        let before = momentum(&game, sharp) + momentum(&game, other);
+        let sharp_before = momentum(&game, sharp);
+
+        assert!(game.handle_collision(collision));
+        let after = momentum(&game, sharp) + momentum(&game, other);
+        assert!(before.distance(after) < 1e-3 * before.length());
+        assert!(game.game_objects[&other].body().angular_velocity != 0.);
+
+        // friction impulse bounded by the coefficient times the normal impulse
+        let impulse = momentum(&game, sharp) - sharp_before;
+        let friction =
+            (game.game_objects[&sharp].friction() * game.game_objects[&other].friction()).sqrt();
+        assert!(impulse.y > 0.);
+        assert!(impulse.x < 0.);
+        assert!(-impulse.x <= friction * impulse.y * 1.001);
+    }
+
+    #[test]
+    fn overlapping_bodies_are_pushed_apart_by_depth() {
+        let depth = 0.2;
+        let (mut game, collision) = corner_hit(2. - depth, vec2(0., -1.));
+        let (sharp, other) = (collision.sharp_obj.0, collision.other_obj.0);
+        let gap = |game: &Game| {
+            game.game_objects[&sharp].body().position.y
+                - game.game_objects[&other].body().position.y
+        };
+        let before = gap(&game);
+
+        assert!(game.handle_collision(collision));
+        let pushed = gap(&game) - before;
+        assert!((pushed - (depth - PENETRATION_SLOP) * POSITION_CORRECTION).abs() < 1e-4);
+    }
+}
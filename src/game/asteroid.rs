@@ -1,5 +1,15 @@
 use crate::prelude::*;
 use glam::Vec2;
+use rand::Rng;
+use rand_chacha::ChaChaRng;
+
+/// Destroyed asteroids at least this large split into fragments
+pub const FRACTURE_MIN_RADIUS: f32 = 4.;
+/// Speed at which the fragments move apart from each other
+const FRACTURE_SPEED: f32 = 2.;
+/// Furthest a corner of the shape generated by `Asteroid::new` lies from the center, relative to
+/// the radius
+const MAX_CORNER_DISTANCE: f32 = 1.1;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Asteroid {
@@ -10,20 +20,29 @@ pub struct Asteroid {
 }
 
 impl Asteroid {
-    pub fn new(pos: Vec2, vel: Vec2, time: f32, radius: f32, material: Material) -> Self {
-        let segments = rand::random::<usize>() % 30 + 10;
+    /// Generates an irregular asteroid shape, all randomness is drawn from `rng` so the result
+    /// is reproducible for a given rng state
+    pub fn new(
+        pos: Vec2,
+        vel: Vec2,
+        time: f32,
+        radius: f32,
+        material: Material,
+        rng: &mut ChaChaRng,
+    ) -> Self {
+        let segments = rng.gen::<usize>() % 30 + 10;
 
         let mut bounds = vec![];
 
         for i in 0..segments {
             let angle = i as f32 / segments as f32 * 2. * PI;
 
-            let dist_mp = rand::random::<f32>() / 5. + 0.9;
+            let dist_mp = rng.gen::<f32>() / 5. + 0.9;
 
             bounds.push(vec2(angle.cos(), angle.sin()) * radius * dist_mp);
         }
 
-        let body = GameObjectBody::new(pos, vel, rand::random::<f32>() * PI * 2., time, bounds);
+        let body = GameObjectBody::new(pos, vel, rng.gen::<f32>() * PI * 2., time, bounds);
 
         Self {
             body,
@@ -35,6 +54,81 @@ impl Asteroid {
     pub fn mass(&self) -> f32 {
         self.radius * self.radius * std::f32::consts::PI * self.material.density()
     }
+    /// Splits the asteroid into 2 to 4 smaller asteroids of the same material.
+    /// Mass and momentum are conserved, the fragments keep the rotation of the asteroid and
+    /// drift apart. They are spread out far enough not to overlap, corners starting inside
+    /// another body wouldn't collide. Returns nothing for asteroids smaller than
+    /// `FRACTURE_MIN_RADIUS`.
+    pub fn fracture(&self, rng: &mut ChaChaRng) -> Vec<GameObjectEffect> {
+        if self.radius < FRACTURE_MIN_RADIUS {
+            return vec![];
+        }
+        let count = rng.gen_range(2..=4);
+        let weights = (0..count)
+            .map(|_| rng.gen::<f32>() + 0.5)
+            .collect::<Vec<_>>();
+        let total_weight = weights.iter().sum::<f32>();
+        let start_angle = rng.gen::<f32>() * 2. * PI;
+
+        // areas are proportional to the weights, so the total mass stays the same
+        let radii = weights
+            .iter()
+            .map(|weight| self.radius * (weight / total_weight).sqrt())
+            .collect::<Vec<_>>();
+        // neighbours are the closest fragments, keep them apart on a common circle
+        let distance = (0..count)
+            .map(|i| radii[i] + radii[(i + 1) % count])
+            .fold(0., f32::max)
+            * MAX_CORNER_DISTANCE
+            / (2. * (PI / count as f32).sin());
+        let mut fragments = radii
+            .into_iter()
+            .enumerate()
+            .map(|(i, radius)| {
+                let direction = Vec2::from_angle(start_angle + i as f32 / count as f32 * 2. * PI);
+                (radius, direction, direction * distance)
+            })
+            .collect::<Vec<_>>();
+
+        // shift the fragments so their common center of mass stays where the asteroid was
+        let area = |radius: f32| radius * radius;
+        let center = fragments
+            .iter()
+            .map(|(radius, _, offset)| *offset * area(*radius))
+            .sum::<Vec2>()
+            / area(self.radius);
+        for (_, _, offset) in &mut fragments {
+            *offset -= center;
+        }
+        let mean_direction = fragments
+            .iter()
+            .map(|(radius, direction, _)| *direction * area(*radius))
+            .sum::<Vec2>()
+            / area(self.radius);
+
+        fragments
+            .into_iter()
+            .map(|(radius, direction, offset)| {
+                let position = self.body.position + offset;
+                let velocity = self.body.point_velocity(position)
+                    + (direction - mean_direction) * FRACTURE_SPEED;
+                let mut fragment = Asteroid::new(
+                    position,
+                    velocity,
+                    self.body.cur_time,
+                    radius,
+                    self.material,
+                    rng,
+                );
+                fragment.body.angular_velocity = self.body.angular_velocity;
+                GameObjectEffect::SpawnAsteroid(fragment)
+            })
+            .collect()
+    }
+    /// Moment of inertia of a disk
+    pub fn inertia(&self) -> f32 {
+        0.5 * self.mass() * self.radius * self.radius
+    }
 }
 
 impl Asteroid {
@@ -55,3 +149,92 @@ impl Asteroid {
         self.health
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn fragments(asteroid: &Asteroid, rng: &mut ChaChaRng) -> Vec<Asteroid> {
+        asteroid
+            .fracture(rng)
+            .into_iter()
+            .map(|effect| match effect {
+                GameObjectEffect::SpawnAsteroid(fragment) => fragment,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fracture_conserves_mass_and_momentum() {
+        let mut rng = ChaChaRng::seed_from_u64(3);
+        let mut asteroid = Asteroid::new(
+            vec2(40., -10.),
+            vec2(3., 1.),
+            2.,
+            12.,
+            Material::Iron,
+            &mut rng,
+        );
+        asteroid.body.angular_velocity = 0.3;
+        let momentum = asteroid.body.velocity * asteroid.mass();
+
+        let pieces = fragments(&asteroid, &mut rng);
+        assert!((2..=4).contains(&pieces.len()));
+        let mass = pieces.iter().map(|piece| piece.mass()).sum::<f32>();
+        assert!((mass - asteroid.mass()).abs() < 1e-3 * asteroid.mass());
+        let total = pieces
+            .iter()
+            .map(|piece| piece.body.velocity * piece.mass())
+            .sum::<Vec2>();
+        assert!(total.distance(momentum) < 1e-3 * momentum.length());
+
+        // no fragment starts overlapping another one
+        let extent = |piece: &Asteroid| {
+            piece
+                .body
+                .bounds
+                .iter()
+                .map(|corner| corner.length())
+                .fold(0., f32::max)
+        };
+        for (i, a) in pieces.iter().enumerate() {
+            for b in &pieces[i + 1..] {
+                let distance = a.body.position.distance(b.body.position);
+                assert!(distance >= extent(a) + extent(b));
+            }
+        }
+    }
+
+    #[test]
+    fn fracture_ends_below_min_radius() {
+        let fracture_fully = |seed| {
+            let mut rng = ChaChaRng::seed_from_u64(seed);
+            let asteroid = Asteroid::new(
+                Vec2::ZERO,
+                Vec2::ZERO,
+                0.,
+                20.,
+                Material::Silicates,
+                &mut rng,
+            );
+            let mut pending = vec![asteroid];
+            let mut finished = vec![];
+            while let Some(asteroid) = pending.pop() {
+                let pieces = fragments(&asteroid, &mut rng);
+                if pieces.is_empty() {
+                    assert!(asteroid.radius < FRACTURE_MIN_RADIUS);
+                    finished.push(asteroid.body.position);
+                }
+                for piece in pieces {
+                    assert!(piece.radius < asteroid.radius);
+                    pending.push(piece);
+                }
+            }
+            finished
+        };
+        assert_eq!(fracture_fully(5), fracture_fully(5));
+        assert_ne!(fracture_fully(5), fracture_fully(6));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::prelude::*;
+
+use super::spacecraft::ComponentCatalogue;
+use super::HullClass;
+
+/// Format version of `BlueprintFile`, files of newer versions are rejected
+pub const BLUEPRINT_VERSION: u32 = 1;
+
+pub type BlueprintId = u32;
+
+/// Spacecraft design saved in the library of a player
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Blueprint {
+    pub name: String,
+    pub structure: SpacecraftStructure,
+}
+
+/// Portable form of a blueprint for sharing designs between players and games. Components are
+/// referred to by their catalogue name as well, so the design survives catalogues which number
+/// them differently.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BlueprintFile {
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub hull_class: HullClass,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub components: Vec<BlueprintComponent>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlueprintComponent {
+    pub name: String,
+    pub component_type: ComponentType,
+    pub position: IVec2,
+    pub orientation: Orientation,
+}
+
+#[derive(Debug)]
+pub enum BlueprintError {
+    InvalidFormat(String),
+    UnsupportedVersion(u32),
+}
+
+impl Blueprint {
+    pub fn new(name: String, structure: SpacecraftStructure) -> Self {
+        Self { name, structure }
+    }
+    /// Writes the blueprint in the format of `serialize_str`, components the catalogue doesn't
+    /// define are left out
+    pub fn export(&self, catalogue: &ComponentCatalogue) -> Result<String, std::io::Error> {
+        let components = self
+            .structure
+            .component_placeholders
+            .iter()
+            .filter_map(|placeholder| {
+                let definition = catalogue.get(placeholder.component_type)?;
+                Some(BlueprintComponent {
+                    name: definition.name.clone(),
+                    component_type: placeholder.component_type,
+                    position: placeholder.position,
+                    orientation: placeholder.orientation,
+                })
+            })
+            .collect();
+        serialize_str(&BlueprintFile {
+            version: BLUEPRINT_VERSION,
+            name: self.name.clone(),
+            hull_class: self.structure.hull_class,
+            tags: self.structure.tags.clone(),
+            components,
+        })
+    }
+    /// Reads a blueprint written by `export`, possibly with another catalogue. A component keeps
+    /// its type if the catalogue defines it under the same name, otherwise it takes the type of
+    /// the component with its name. Components the catalogue has no name for are left out of the
+    /// blueprint and returned.
+    pub fn import(
+        data: &str,
+        catalogue: &ComponentCatalogue,
+    ) -> Result<(Self, Vec<BlueprintComponent>), BlueprintError> {
+        let file: BlueprintFile =
+            deserialize_str(data).map_err(|e| BlueprintError::InvalidFormat(e.to_string()))?;
+        if file.version > BLUEPRINT_VERSION {
+            return Err(BlueprintError::UnsupportedVersion(file.version));
+        }
+
+        let mut structure = SpacecraftStructure::new();
+        structure.component_placeholders.clear();
+        structure.hull_class = file.hull_class;
+        structure.tags = file.tags;
+        let mut missing = vec![];
+        for component in file.components {
+            let component_type = if catalogue
+                .get(component.component_type)
+                .is_some_and(|definition| definition.name == component.name)
+            {
+                Some(component.component_type)
+            } else {
+                catalogue
+                    .components
+                    .iter()
+                    .find(|(_, definition)| definition.name == component.name)
+                    .map(|(component_type, _)| *component_type)
+            };
+            match component_type {
+                Some(component_type) => {
+                    structure
+                        .component_placeholders
+                        .push(ComponentPlaceholder::new(
+                            component_type,
+                            component.position,
+                            component.orientation,
+                        ))
+                }
+                None => missing.push(component),
+            }
+        }
+        Ok((Self::new(file.name, structure), missing))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+    use crate::game::GameCmdExecutionError;
+
+    #[test]
+    fn import_follows_renumbered_components() {
+        let mut structure = SpacecraftBuilder::new()
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, 1), Orientation::Up)
+            .with(ComponentType::REACTOR, ivec2(1, 0), Orientation::Up)
+            .structure;
+        structure.hull_class = HullClass::Frigate;
+        let data = Blueprint::new("Scout".to_string(), structure)
+            .export(&ComponentCatalogue::default())
+            .unwrap();
+
+        let mut catalogue = ComponentCatalogue::default();
+        let block = catalogue
+            .components
+            .remove(&ComponentType::STEEL_BLOCK)
+            .unwrap();
+        catalogue.components.insert(ComponentType(100), block);
+        catalogue.components.remove(&ComponentType::REACTOR);
+
+        let (blueprint, missing) = Blueprint::import(&data, &catalogue).unwrap();
+        assert_eq!(blueprint.name, "Scout");
+        assert_eq!(blueprint.structure.hull_class, HullClass::Frigate);
+        let types = blueprint
+            .structure
+            .component_placeholders
+            .iter()
+            .map(|placeholder| placeholder.component_type)
+            .collect::<Vec<_>>();
+        assert_eq!(types, vec![ComponentType::CENTRAL, ComponentType(100)]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].component_type, ComponentType::REACTOR);
+
+        let newer = data.replace("version: 1", "version: 2");
+        assert!(matches!(
+            Blueprint::import(&newer, &catalogue),
+            Err(BlueprintError::UnsupportedVersion(2))
+        ));
+    }
+
+    #[test]
+    fn players_build_from_their_library() {
+        let mut game = Game::with_seed(0);
+        for cmd in [
+            GameCmd::AddPlayer(1),
+            GameCmd::AddPlayer(2),
+            GameCmd::SpawnStarBase(1, Vec2::ZERO, Vec2::ZERO),
+            GameCmd::GiveMaterials(
+                1,
+                [Material::Nickel, Material::Iron, Material::Silicates]
+                    .into_iter()
+                    .map(|material| (material, 100.))
+                    .collect(),
+            ),
+        ] {
+            game.execute_cmd(User::Server, cmd).unwrap();
+        }
+        let star_base_id = game.sorted_game_object_ids()[0];
+
+        let save = GameCmd::SaveBlueprint(1, "Probe".to_string(), SpacecraftStructure::new());
+        assert!(matches!(
+            game.execute_cmd(User::Player(2), save.clone()),
+            Err(GameCmdExecutionError::NotAuthorized)
+        ));
+        game.execute_cmd(User::Player(1), save.clone()).unwrap();
+        game.execute_cmd(User::Player(1), save).unwrap();
+        game.execute_cmd(User::Player(1), GameCmd::DeleteBlueprint(1, 0))
+            .unwrap();
+        game.execute_cmd(
+            User::Player(1),
+            GameCmd::RenameBlueprint(1, 1, "Scout".to_string()),
+        )
+        .unwrap();
+        assert_eq!(game.players[&1].list_blueprints(), vec![(1, "Scout")]);
+
+        assert!(matches!(
+            game.execute_cmd(User::Player(1), GameCmd::BuildBlueprint(star_base_id, 0, 0)),
+            Err(GameCmdExecutionError::InvalidId)
+        ));
+        game.execute_cmd(User::Player(1), GameCmd::BuildBlueprint(star_base_id, 1, 0))
+            .unwrap();
+        let Some(GameObject::StarBase(star_base)) = game.game_objects.get(&star_base_id) else {
+            unreachable!()
+        };
+        assert_eq!(star_base.hangars[0].building_queue.len(), 1);
+    }
+}
@@ -0,0 +1,195 @@
+use super::*;
+
+/// FNV-1a, used instead of `DefaultHasher` because its output must not change between
+/// platforms or compiler versions
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl std::io::Write for Fnv64 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Stable digest of the bincode representation of `value`
+pub fn stable_hash<T: Serialize + ?Sized>(value: &T) -> u64 {
+    let mut hasher = Fnv64::new();
+    bincode::serialize_into(&mut hasher, value).unwrap();
+    hasher.0
+}
+
+/// Per subsystem digests of a game state at a given frame.
+/// Comparing two checksums of the same frame tells which part of the simulation diverged.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StateChecksum {
+    pub frame: usize,
+    pub hash: u64,
+    pub players: u64,
+    pub time_elapsed: f32,
+    pub rng: u64,
+    pub game_objects: BTreeMap<GameObjectId, GameObjectChecksum>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct GameObjectChecksum {
+    pub hash: u64,
+    /// Digest of every field, nested structures are split one level deep (e.g. `body.position`)
+    pub fields: BTreeMap<String, u64>,
+}
+
+impl GameObjectChecksum {
+    pub fn new(game_object: &GameObject) -> Self {
+        let mut fields = BTreeMap::new();
+        // externally tagged enum, the only entry is the variant itself
+        if let Ok(serde_json::Value::Object(variant)) = serde_json::to_value(game_object) {
+            for (variant_name, value) in variant {
+                let serde_json::Value::Object(object_fields) = value else {
+                    fields.insert(variant_name, stable_hash(&value));
+                    continue;
+                };
+                for (name, value) in object_fields {
+                    match value {
+                        serde_json::Value::Object(sub_fields) => {
+                            for (sub_name, sub_value) in sub_fields {
+                                fields.insert(
+                                    format!("{}.{}", name, sub_name),
+                                    stable_hash(&sub_value),
+                                );
+                            }
+                        }
+                        value => {
+                            fields.insert(name, stable_hash(&value));
+                        }
+                    }
+                }
+            }
+        }
+        Self {
+            hash: stable_hash(game_object),
+            fields,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Desync {
+    Frame(usize, usize), // own, other
+    Players,
+    TimeElapsed(f32, f32),
+    Rng,
+    MissingGameObject(GameObjectId), // present only in the other state
+    ExtraGameObject(GameObjectId),   // present only in own state
+    GameObjectFields(GameObjectId, Vec<String>),
+}
+
+impl StateChecksum {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            frame: game.sync.frame,
+            hash: game.state_hash(),
+            players: stable_hash(&game.players.iter().collect::<BTreeMap<_, _>>()),
+            time_elapsed: game.time_elapsed,
+            rng: stable_hash(&game.rng),
+            game_objects: game
+                .game_objects
+                .iter()
+                .map(|(id, game_object)| (*id, GameObjectChecksum::new(game_object)))
+                .collect(),
+        }
+    }
+    /// Lists every subsystem in which `self` differs from `other`, empty if the states match
+    pub fn diff(&self, other: &StateChecksum) -> Vec<Desync> {
+        let mut result = vec![];
+        if self.frame != other.frame {
+            result.push(Desync::Frame(self.frame, other.frame));
+        }
+        if self.hash == other.hash {
+            return result;
+        }
+        if self.players != other.players {
+            result.push(Desync::Players);
+        }
+        if self.time_elapsed.to_bits() != other.time_elapsed.to_bits() {
+            result.push(Desync::TimeElapsed(self.time_elapsed, other.time_elapsed));
+        }
+        if self.rng != other.rng {
+            result.push(Desync::Rng);
+        }
+        for (id, checksum) in &self.game_objects {
+            let Some(other_checksum) = other.game_objects.get(id) else {
+                result.push(Desync::ExtraGameObject(*id));
+                continue;
+            };
+            if checksum.hash == other_checksum.hash {
+                continue;
+            }
+            let differing_fields = checksum
+                .fields
+                .keys()
+                .chain(other_checksum.fields.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter(|name| checksum.fields.get(*name) != other_checksum.fields.get(*name))
+                .cloned()
+                .collect();
+            result.push(Desync::GameObjectFields(*id, differing_fields));
+        }
+        for id in other.game_objects.keys() {
+            if !self.game_objects.contains_key(id) {
+                result.push(Desync::MissingGameObject(*id));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_names_the_differing_fields() {
+        let game = || {
+            let mut game = Game::with_seed(3);
+            game.execute_cmd(User::Server, GameCmd::AddPlayer(1))
+                .unwrap();
+            for i in 0..5 {
+                let cmd = GameCmd::SpawnRandomAsteroid(vec2(i as f32 * 30., 0.), Vec2::ZERO);
+                game.execute_cmd(User::Server, cmd).unwrap();
+            }
+            game.update(0.05);
+            game
+        };
+        let own = game();
+        let mut other = game();
+        assert_eq!(own.state_checksum().diff(&other.state_checksum()), vec![]);
+
+        let id = other.sorted_game_object_ids()[2];
+        let body = other.game_objects.get_mut(&id).unwrap().body_mut();
+        body.velocity.x += 1.;
+        other
+            .players
+            .get_mut(&1)
+            .unwrap()
+            .give_materials(vec![(Material::Iron, 1.)]);
+        assert_eq!(
+            own.state_checksum().diff(&other.state_checksum()),
+            vec![
+                Desync::Players,
+                Desync::GameObjectFields(id, vec!["body.velocity".to_string()])
+            ]
+        );
+    }
+}
@@ -1,4 +1,7 @@
+mod broad_phase;
+
 use super::*;
+pub use broad_phase::*;
 
 #[derive(PartialEq, PartialOrd, Debug, Clone, Copy)]
 pub struct CollisionInfo {
@@ -50,50 +53,206 @@ pub fn sat_collision_detect(convex_hull1: &Vec<Vec2>, convex_hull2: &Vec<Vec2>)
     return true;
 }
 
-/// Checks whether obj 1 collides with obj 2 with one of its corners
-/// Don't give me vertical lines pls or I'll do some weird stuff
-pub fn check_sharp_collision(
-    sharp_points: Vec<Vec2>,
-    other_points: Vec<Vec2>,
-    velocity: Vec2,
-    max_t: f32,
-) -> Option<(f32, usize, usize)> {
-    let mut collision: Option<(f32, usize, usize)> = None;
+/// Solves `origin + dir * t = a + (b - a) * s` for `s` in [0, 1].
+/// Returns `(t, s)`, None for parallel lines or if the line misses the segment.
+pub fn ray_segment_intersection(origin: Vec2, dir: Vec2, a: Vec2, b: Vec2) -> Option<(f32, f32)> {
+    let edge = b - a;
+    let denominator = dir.perp_dot(edge);
+    if denominator.abs() <= f32::EPSILON * dir.length() * edge.length() {
+        return None;
+    }
+    let offset = a - origin;
+    let t = offset.perp_dot(edge) / denominator;
+    let s = offset.perp_dot(dir) / denominator;
+    if !(0. ..=1.).contains(&s) {
+        return None;
+    }
+    Some((t, s))
+}
 
-    for (i, p) in sharp_points.into_iter().enumerate() {
-        for j in 0..other_points.len() {
-            let a = other_points[j];
-            let b = other_points[(j + 1) % other_points.len()];
-            let v = velocity;
+pub fn closest_point_on_segment(point: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let edge = b - a;
+    let length_squared = edge.length_squared();
+    if length_squared == 0. {
+        return a;
+    }
+    a + edge * ((point - a).dot(edge) / length_squared).clamp(0., 1.)
+}
 
-            let slope_1 = (b.y - a.y) / (b.x - a.x);
-            let y_1 = a.y - a.x * slope_1;
-            let slope_2 = (v.y) / (v.x);
-            let y_2 = p.y - p.x * slope_2;
+/// Works for both clockwise and counter-clockwise polygons
+pub fn point_in_convex_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+    let mut sign = 0.;
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        let cross = (b - a).perp_dot(point - a);
+        if cross == 0. {
+            continue;
+        }
+        if sign == 0. {
+            sign = cross.signum();
+        } else if cross.signum() != sign {
+            return false;
+        }
+    }
+    polygon.len() > 2
+}
 
-            let intercept = (y_2 - y_1) / (slope_1 - slope_2);
+/// Point of the convex polygon closest to `point`, the point itself if it lies inside.
+/// None for an empty polygon.
+pub fn closest_point_on_polygon(point: Vec2, polygon: &[Vec2]) -> Option<Vec2> {
+    if point_in_convex_polygon(point, polygon) {
+        return Some(point);
+    }
+    (0..polygon.len())
+        .map(|i| closest_point_on_segment(point, polygon[i], polygon[(i + 1) % polygon.len()]))
+        .min_by(|a, b| a.distance(point).partial_cmp(&b.distance(point)).unwrap())
+}
 
-            if intercept < a.x.min(b.x) || intercept > a.x.max(b.x) {
-                continue;
+/// Whether the segment from `a` to `b` crosses an edge of the convex polygon or lies inside it
+pub fn segment_intersects_polygon(a: Vec2, b: Vec2, polygon: &[Vec2]) -> bool {
+    if point_in_convex_polygon(a, polygon) {
+        return true;
+    }
+    (0..polygon.len()).any(|i| {
+        ray_segment_intersection(a, b - a, polygon[i], polygon[(i + 1) % polygon.len()])
+            .is_some_and(|(t, _)| (0. ..=1.).contains(&t))
+    })
+}
+
+/// First point where the ray `origin + dir * t`, `t >= 0`, enters the convex polygon.
+/// Returns `(t, normal)` with the normal of the hit edge pointing against the ray, a ray starting
+/// inside the polygon hits at `t = 0` with a zero normal. None if the ray misses.
+pub fn ray_cast_polygon(origin: Vec2, dir: Vec2, polygon: &[Vec2]) -> Option<(f32, Vec2)> {
+    if point_in_convex_polygon(origin, polygon) {
+        return Some((0., Vec2::ZERO));
+    }
+    (0..polygon.len())
+        .filter_map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let (t, _) = ray_segment_intersection(origin, dir, a, b)?;
+            if t < 0. {
+                return None;
             }
+            let normal = (b - a).perp().normalize_or_zero();
+            let normal = if normal.dot(dir) > 0. {
+                -normal
+            } else {
+                normal
+            };
+            Some((t, normal))
+        })
+        .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap())
+}
+
+/// Distance under which a corner counts as touching an edge
+pub const CONTACT_TOLERANCE: f32 = 1e-3;
+/// Approach speed under which touching bodies count as resting instead of colliding. Impulses
+/// for slower contacts vanish in rounding and would leave them approaching forever.
+pub const RESTING_SPEED: f32 = 1e-2;
+const MAX_ADVANCEMENT_STEPS: usize = 64;
 
-            let time = (intercept - p.x) / (v.x);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    /// Time relative to the `cur_time` of the bodies
+    pub time: f32,
+    pub point: Vec2,
+    /// Points out of the other body towards the sharp one
+    pub normal: Vec2,
+    pub sharp_point: usize,
+    pub other_line: usize,
+}
+
+/// Earliest time within `max_t` at which a corner of `sharp` touches an edge of `other`.
+/// Both bodies move with constant velocity and angular velocity (see `GameObjectBody::predicted`)
+/// and must share the same `cur_time`. Uses conservative advancement: the distance of the
+/// corners to the other polygon can't shrink faster than the sum of the linear and rotational
+/// speeds, so advancing by distance / speed never skips a contact.
+/// Corners which already start inside `other` are ignored.
+pub fn time_of_impact(
+    sharp: &GameObjectBody,
+    other: &GameObjectBody,
+    max_t: f32,
+) -> Option<Contact> {
+    if sharp.bounds.is_empty() || other.bounds.len() < 3 {
+        return None;
+    }
+
+    let max_speed = (sharp.velocity - other.velocity).length()
+        + sharp.angular_velocity.abs() * sharp.radius()
+        + other.angular_velocity.abs() * other.radius();
+    if max_speed <= 0. {
+        return None;
+    }
 
-            if time.is_nan() || time <= 0. || time >= max_t {
+    let other_start = other.world_bounds();
+    let active = sharp
+        .world_bounds()
+        .into_iter()
+        .map(|p| !point_in_convex_polygon(p, &other_start))
+        .collect::<Vec<_>>();
+
+    let mut t = 0.;
+    for _ in 0..MAX_ADVANCEMENT_STEPS {
+        let sharp_t = sharp.predicted(sharp.cur_time + t);
+        let other_t = other.predicted(other.cur_time + t);
+        let sharp_points = sharp_t.world_bounds();
+        let other_points = other_t.world_bounds();
+
+        let mut closest: Option<(f32, usize, usize, Vec2)> = None;
+        for (i, p) in sharp_points.iter().enumerate() {
+            if !active[i] {
                 continue;
             }
-
-            if let Some(cur_answer) = &mut collision {
-                if time < cur_answer.0 {
-                    *cur_answer = (time, i, j);
+            for j in 0..other_points.len() {
+                let a = other_points[j];
+                let b = other_points[(j + 1) % other_points.len()];
+                let on_edge = closest_point_on_segment(*p, a, b);
+                let distance = p.distance(on_edge);
+                if !closest.is_some_and(|c| c.0 <= distance) {
+                    closest = Some((distance, i, j, on_edge));
                 }
-            } else {
-                collision = Some((time, i, j));
             }
         }
+        let (distance, i, j, on_edge) = closest?;
+
+        if distance <= CONTACT_TOLERANCE {
+            let point = sharp_points[i];
+            let a = other_points[j];
+            let b = other_points[(j + 1) % other_points.len()];
+            let mut normal = (b - a).perp().normalize_or_zero();
+            if normal.dot(on_edge - other_t.position) < 0. {
+                normal = -normal;
+            }
+            if approach_velocity(&sharp_t, &other_t, point, normal) < -RESTING_SPEED {
+                return Some(Contact {
+                    time: t,
+                    point,
+                    normal,
+                    sharp_point: i,
+                    other_line: j,
+                });
+            }
+        }
+
+        // touching corners which move apart must not stall the advancement
+        t += distance.max(CONTACT_TOLERANCE) / max_speed;
+        if t >= max_t {
+            return None;
+        }
     }
+    None
+}
 
-    collision
+/// Speed at which the sharp body moves into the other one at `point` along `normal`, which points
+/// out of the other body. Negative while approaching.
+pub fn approach_velocity(
+    sharp: &GameObjectBody,
+    other: &GameObjectBody,
+    point: Vec2,
+    normal: Vec2,
+) -> f32 {
+    (sharp.point_velocity(point) - other.point_velocity(point)).dot(normal)
 }
 
 // Helper function to find the minimum and maximum extent of a shape when projected onto an axis
@@ -162,3 +321,103 @@ pub fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
 
     stack
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(position: Vec2, velocity: Vec2, size: f32) -> GameObjectBody {
+        GameObjectBody::new(
+            position,
+            velocity,
+            0.,
+            0.,
+            vec![
+                vec2(size, size),
+                vec2(-size, size),
+                vec2(-size, -size),
+                vec2(size, -size),
+            ],
+        )
+    }
+
+    #[test]
+    fn axis_aligned_edges() {
+        // moving straight right into a vertical edge
+        let sharp = square(vec2(0., 0.), vec2(10., 0.), 1.);
+        let other = square(vec2(5., 0.5), Vec2::ZERO, 1.);
+        let contact = time_of_impact(&sharp, &other, 1.).unwrap();
+        assert!((contact.time - 0.3).abs() < 1e-3);
+        assert!((contact.normal - vec2(-1., 0.)).length() < 1e-4);
+        assert!((contact.point.x - 4.).abs() < 1e-2);
+
+        // the same along a horizontal edge
+        let sharp = square(vec2(0.5, 0.), vec2(0., -10.), 1.);
+        let other = square(vec2(0., -5.), Vec2::ZERO, 1.);
+        let contact = time_of_impact(&sharp, &other, 1.).unwrap();
+        assert!((contact.time - 0.3).abs() < 1e-3);
+        assert!((contact.normal - vec2(0., 1.)).length() < 1e-4);
+    }
+
+    #[test]
+    fn ray_hits_nearest_edge() {
+        let bounds = square(vec2(5., 0.), Vec2::ZERO, 1.).world_bounds();
+        let (t, normal) = ray_cast_polygon(Vec2::ZERO, vec2(2., 0.), &bounds).unwrap();
+        assert!((t - 2.).abs() < 1e-5);
+        assert!((normal - vec2(-1., 0.)).length() < 1e-5);
+
+        assert_eq!(ray_cast_polygon(Vec2::ZERO, vec2(-1., 0.), &bounds), None);
+        assert_eq!(ray_cast_polygon(Vec2::ZERO, vec2(1., 1.), &bounds), None);
+        assert_eq!(
+            ray_cast_polygon(vec2(5., 0.5), vec2(1., 0.), &bounds),
+            Some((0., Vec2::ZERO))
+        );
+    }
+
+    #[test]
+    fn parallel_motion() {
+        // sliding past each other without ever touching
+        let sharp = square(vec2(0., 0.), vec2(10., 0.), 1.);
+        let other = square(vec2(5., 2.5), Vec2::ZERO, 1.);
+        assert_eq!(time_of_impact(&sharp, &other, 1.), None);
+
+        // moving apart
+        let sharp = square(vec2(0., 0.), vec2(-10., 0.), 1.);
+        let other = square(vec2(2. + CONTACT_TOLERANCE / 2., 0.), Vec2::ZERO, 1.);
+        assert_eq!(time_of_impact(&sharp, &other, 1.), None);
+    }
+
+    #[test]
+    fn spinning_bodies() {
+        // a long thin rod spinning in place sweeps over a box next to it
+        let mut rod = GameObjectBody::new(
+            Vec2::ZERO,
+            Vec2::ZERO,
+            0.,
+            0.,
+            vec![
+                vec2(10., 0.1),
+                vec2(-10., 0.1),
+                vec2(-10., -0.1),
+                vec2(10., -0.1),
+            ],
+        );
+        rod.angular_velocity = 20.;
+        let other = square(vec2(0., 6.), Vec2::ZERO, 1.);
+
+        // the corner of the box is hit by the edge of the rod
+        let contact = time_of_impact(&other, &rod, 1.).unwrap();
+        // the rod reaches the box before a quarter turn
+        assert!(contact.time > 0. && contact.time < PI / 2. / 20.);
+        assert!((contact.point - vec2(1., 5.)).length() < 1e-2);
+        assert!(point_in_convex_polygon(
+            contact.point - contact.normal * 0.01,
+            &rod.predicted(contact.time).world_bounds()
+        ));
+        // none of the rod's own corners ever touches the box
+        assert_eq!(time_of_impact(&rod, &other, 1.), None);
+
+        // too slow to reach it in time
+        assert_eq!(time_of_impact(&other, &rod, 0.01), None);
+    }
+}
@@ -0,0 +1,306 @@
+use super::*;
+
+pub const DEFAULT_CELL_SIZE: f32 = 20.;
+/// Objects spanning more cells than this are kept in a separate list checked by every query
+const MAX_OBJECT_CELLS: i64 = 1024;
+
+/// Axis aligned bounding box
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+    pub fn from_points(points: impl IntoIterator<Item = Vec2>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| {
+            Self::new(aabb.min.min(point), aabb.max.max(point))
+        }))
+    }
+    pub fn from_circle(center: Vec2, radius: f32) -> Self {
+        Self::new(center - Vec2::splat(radius), center + Vec2::splat(radius))
+    }
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+    pub fn distance_to(&self, point: Vec2) -> f32 {
+        point.clamp(self.min, self.max).distance(point)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BroadPhaseEntry {
+    aabb: Aabb,
+    /// Inclusive range of occupied cells, None for oversized objects
+    cells: Option<(IVec2, IVec2)>,
+}
+
+/// Uniform grid over the bounding boxes of game objects.
+/// Queries return candidates whose boxes overlap, exact shape tests are up to the caller.
+/// All results are sorted by id so they can be used in the deterministic simulation.
+#[derive(Clone, Debug)]
+pub struct BroadPhase {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<GameObjectId>>,
+    entries: BTreeMap<GameObjectId, BroadPhaseEntry>,
+    oversized: BTreeSet<GameObjectId>,
+}
+
+impl Default for BroadPhase {
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl BroadPhase {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: BTreeMap::new(),
+            oversized: BTreeSet::new(),
+        }
+    }
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn contains(&self, id: GameObjectId) -> bool {
+        self.entries.contains_key(&id)
+    }
+    pub fn aabb(&self, id: GameObjectId) -> Option<Aabb> {
+        self.entries.get(&id).map(|entry| entry.aabb)
+    }
+    pub fn ids(&self) -> impl Iterator<Item = GameObjectId> + '_ {
+        self.entries.keys().copied()
+    }
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.entries.clear();
+        self.oversized.clear();
+    }
+
+    fn cell_range(&self, aabb: &Aabb) -> Option<(IVec2, IVec2)> {
+        let min = (aabb.min / self.cell_size).floor();
+        let max = (aabb.max / self.cell_size).floor();
+        if !min.is_finite() || !max.is_finite() {
+            return None;
+        }
+        let cells = (max.x - min.x + 1.) as i64 * (max.y - min.y + 1.) as i64;
+        if cells > MAX_OBJECT_CELLS {
+            return None;
+        }
+        Some((min.as_ivec2(), max.as_ivec2()))
+    }
+
+    fn cells_in(range: (IVec2, IVec2)) -> impl Iterator<Item = IVec2> {
+        let (min, max) = range;
+        (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| ivec2(x, y)))
+    }
+
+    /// Inserts an object or moves an already tracked one
+    pub fn update(&mut self, id: GameObjectId, aabb: Aabb) {
+        let cells = self.cell_range(&aabb);
+        if let Some(entry) = self.entries.get_mut(&id) {
+            if entry.cells == cells {
+                entry.aabb = aabb;
+                return;
+            }
+        }
+        self.remove(id);
+        match cells {
+            Some(range) => {
+                for cell in Self::cells_in(range) {
+                    self.cells.entry(cell).or_default().push(id);
+                }
+            }
+            None => {
+                self.oversized.insert(id);
+            }
+        }
+        self.entries.insert(id, BroadPhaseEntry { aabb, cells });
+    }
+
+    pub fn remove(&mut self, id: GameObjectId) {
+        let Some(entry) = self.entries.remove(&id) else {
+            return;
+        };
+        match entry.cells {
+            Some(range) => {
+                for cell in Self::cells_in(range) {
+                    if let Some(ids) = self.cells.get_mut(&cell) {
+                        ids.retain(|x| *x != id);
+                        if ids.is_empty() {
+                            self.cells.remove(&cell);
+                        }
+                    }
+                }
+            }
+            None => {
+                self.oversized.remove(&id);
+            }
+        }
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(GameObjectId) -> bool) {
+        let removed = self.ids().filter(|id| !keep(*id)).collect::<Vec<_>>();
+        for id in removed {
+            self.remove(id);
+        }
+    }
+
+    /// Ids of all objects whose bounding box overlaps `area`
+    pub fn query_aabb(&self, area: &Aabb) -> Vec<GameObjectId> {
+        let mut result = BTreeSet::new();
+        match self.cell_range(area) {
+            Some(range) => {
+                for cell in Self::cells_in(range) {
+                    if let Some(ids) = self.cells.get(&cell) {
+                        result.extend(ids.iter().copied());
+                    }
+                }
+                result.extend(self.oversized.iter().copied());
+            }
+            None => result.extend(self.ids()),
+        }
+        result
+            .into_iter()
+            .filter(|id| self.entries[id].aabb.intersects(area))
+            .collect()
+    }
+
+    /// Ids of all objects whose bounding box is at most `radius` away from `center`
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<GameObjectId> {
+        self.query_aabb(&Aabb::from_circle(center, radius))
+            .into_iter()
+            .filter(|id| self.entries[id].aabb.distance_to(center) <= radius)
+            .collect()
+    }
+
+    /// Other objects whose bounding box overlaps the one of `id`
+    pub fn candidates(&self, id: GameObjectId) -> Vec<GameObjectId> {
+        let Some(entry) = self.entries.get(&id) else {
+            return vec![];
+        };
+        let mut result = self.query_aabb(&entry.aabb);
+        result.retain(|other| *other != id);
+        result
+    }
+
+    /// All pairs of overlapping objects, each pair is listed once with the lower id first
+    pub fn pairs(&self) -> Vec<(GameObjectId, GameObjectId)> {
+        let mut result = vec![];
+        for id in self.ids() {
+            for other in self.candidates(id) {
+                if id < other {
+                    result.push((id, other));
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn pairs_match_brute_force() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut broad_phase = BroadPhase::default();
+        let mut aabbs = vec![];
+        for id in 0..3000 {
+            // tall column, the worst case for the old x-axis sweep
+            let min = vec2(rng.gen::<f32>() * 50., rng.gen::<f32>() * 20000.);
+            let aabb = Aabb::new(min, min + vec2(rng.gen::<f32>(), rng.gen::<f32>()) * 8.);
+            broad_phase.update(id, aabb);
+            aabbs.push(aabb);
+        }
+        // one object larger than the whole map
+        broad_phase.update(3000, Aabb::from_circle(Vec2::ZERO, 1e6));
+        aabbs.push(Aabb::from_circle(Vec2::ZERO, 1e6));
+
+        let mut expected = vec![];
+        for i in 0..aabbs.len() {
+            for j in i + 1..aabbs.len() {
+                if aabbs[i].intersects(&aabbs[j]) {
+                    expected.push((i as GameObjectId, j as GameObjectId));
+                }
+            }
+        }
+        let mut pairs = broad_phase.pairs();
+        pairs.sort();
+        assert_eq!(pairs, expected);
+
+        let expected_in_radius = (0..aabbs.len())
+            .filter(|i| aabbs[*i].distance_to(vec2(25., 500.)) <= 30.)
+            .map(|i| i as GameObjectId)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            broad_phase.query_radius(vec2(25., 500.), 30.),
+            expected_in_radius
+        );
+
+        for id in 0..1500 {
+            broad_phase.remove(id);
+        }
+        assert!(broad_phase
+            .pairs()
+            .into_iter()
+            .all(|(a, b)| a >= 1500 && b >= 1500));
+    }
+
+    #[test]
+    fn thousands_of_asteroids() {
+        let mut game = Game::with_seed(0);
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        for id in 0..4000 {
+            let asteroid = Asteroid::new(
+                vec2(rng.gen::<f32>() * 10., id as f32 * 6.),
+                Vec2::random_unit_circle_seed(&mut rng) * 5.,
+                0.,
+                1. + rng.gen::<f32>(),
+                rng.gen(),
+                &mut rng,
+            );
+            game.game_objects.insert(id, GameObject::Asteroid(asteroid));
+        }
+
+        for _ in 0..10 {
+            game.update(0.05);
+        }
+
+        assert_eq!(game.broad_phase.len(), game.game_objects.len());
+        let area = Aabb::new(vec2(-5., 1000.), vec2(15., 1100.));
+        let mut expected = game
+            .game_objects
+            .keys()
+            .copied()
+            .filter(|id| game.game_object_aabb(*id, false).intersects(&area))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(game.objects_in_rect(&area), expected);
+    }
+}
@@ -0,0 +1,303 @@
+use super::*;
+
+/// Number of past frames kept by `Game::save_snapshot` to diff against
+pub const MAX_SNAPSHOTS: usize = 64;
+
+/// Motion related part of `GameObjectBody`, everything except the bounds
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BodyMotion {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    pub cur_time: f32,
+    pub acceleration: Vec2,
+    pub angular_acceleration: f32,
+    pub updated: usize,
+}
+
+impl BodyMotion {
+    pub fn new(body: &GameObjectBody) -> Self {
+        Self {
+            position: body.position,
+            velocity: body.velocity,
+            rotation: body.rotation,
+            angular_velocity: body.angular_velocity,
+            cur_time: body.cur_time,
+            acceleration: body.acceleration,
+            angular_acceleration: body.angular_acceleration,
+            updated: body.updated,
+        }
+    }
+    pub fn apply(&self, body: &mut GameObjectBody) {
+        body.position = self.position;
+        body.velocity = self.velocity;
+        body.rotation = self.rotation;
+        body.angular_velocity = self.angular_velocity;
+        body.cur_time = self.cur_time;
+        body.acceleration = self.acceleration;
+        body.angular_acceleration = self.angular_acceleration;
+        body.updated = self.updated;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum GameObjectChange {
+    Motion(BodyMotion),
+    Bounds(Vec<Vec2>),
+    /// Everything of the spacecraft apart from its body and components, see `Spacecraft::state`
+    SpacecraftState(Box<Spacecraft>),
+    /// Time all components of the spacecraft were updated to
+    ComponentsTime(f32),
+    /// Spacecraft components which were added or changed apart from their time
+    Components(Vec<(ComponentId, Component)>),
+    RemovedComponents(Vec<ComponentId>),
+    /// Anything apart from the body changed, the object is replaced as a whole
+    Replace(GameObject),
+}
+
+impl GameObjectChange {
+    /// Changes which turn `old` into `new`, the cheap body records are used whenever possible
+    /// and spacecrafts only send the components which changed
+    pub fn between(old: &GameObject, new: &GameObject) -> Vec<GameObjectChange> {
+        if let (GameObject::Spacecraft(old), GameObject::Spacecraft(new)) = (old, new) {
+            return Self::between_spacecrafts(old, new);
+        }
+        let mut old_state = old.clone();
+        let mut new_state = new.clone();
+        *old_state.body_mut() = GameObjectBody::default();
+        *new_state.body_mut() = GameObjectBody::default();
+        if std::mem::discriminant(old) != std::mem::discriminant(new)
+            || stable_hash(&old_state) != stable_hash(&new_state)
+        {
+            return vec![GameObjectChange::Replace(new.clone())];
+        }
+        Self::between_bodies(old.body(), new.body())
+    }
+    fn between_bodies(old: &GameObjectBody, new: &GameObjectBody) -> Vec<GameObjectChange> {
+        let mut result = vec![];
+        let motion = BodyMotion::new(new);
+        if BodyMotion::new(old) != motion {
+            result.push(GameObjectChange::Motion(motion));
+        }
+        if old.bounds != new.bounds {
+            result.push(GameObjectChange::Bounds(new.bounds.clone()));
+        }
+        result
+    }
+    fn between_spacecrafts(old: &Spacecraft, new: &Spacecraft) -> Vec<GameObjectChange> {
+        let mut result = Self::between_bodies(&old.body, &new.body);
+        let state = new.state();
+        if stable_hash(&old.state()) != stable_hash(&state) {
+            result.push(GameObjectChange::SpacecraftState(Box::new(state)));
+        }
+        // the components are updated together, so their time is sent once
+        let time = new
+            .components
+            .values()
+            .next()
+            .map(|component| component.body().cur_time)
+            .filter(|time| {
+                new.components
+                    .values()
+                    .all(|component| component.body().cur_time == *time)
+            });
+        let without_time = |component: &Component| {
+            let mut component = component.clone();
+            if let Some(time) = time {
+                component.body_mut().cur_time = time;
+            }
+            stable_hash(&component)
+        };
+        if let Some(time) = time {
+            if old
+                .components
+                .values()
+                .any(|component| component.body().cur_time != time)
+            {
+                result.push(GameObjectChange::ComponentsTime(time));
+            }
+        }
+        let changed = new
+            .components
+            .iter()
+            .filter(|(id, component)| {
+                old.components.get(id).is_none_or(|old_component| {
+                    without_time(old_component) != without_time(component)
+                })
+            })
+            .map(|(id, component)| (*id, component.clone()))
+            .collect::<Vec<_>>();
+        if !changed.is_empty() {
+            result.push(GameObjectChange::Components(changed));
+        }
+        let removed = old
+            .components
+            .keys()
+            .filter(|id| !new.components.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        if !removed.is_empty() {
+            result.push(GameObjectChange::RemovedComponents(removed));
+        }
+        result
+    }
+    pub fn apply(self, game_object: &mut GameObject) {
+        match (self, game_object) {
+            (GameObjectChange::Motion(motion), game_object) => motion.apply(game_object.body_mut()),
+            (GameObjectChange::Bounds(bounds), game_object) => {
+                game_object.body_mut().bounds = bounds
+            }
+            (GameObjectChange::SpacecraftState(state), GameObject::Spacecraft(spacecraft)) => {
+                spacecraft.set_state(*state)
+            }
+            (GameObjectChange::ComponentsTime(time), GameObject::Spacecraft(spacecraft)) => {
+                for component in spacecraft.components.values_mut() {
+                    component.body_mut().cur_time = time;
+                }
+            }
+            (GameObjectChange::Components(components), GameObject::Spacecraft(spacecraft)) => {
+                spacecraft.components.extend(components)
+            }
+            (GameObjectChange::RemovedComponents(ids), GameObject::Spacecraft(spacecraft)) => {
+                for id in ids {
+                    spacecraft.components.remove(&id);
+                }
+            }
+            (GameObjectChange::Replace(new), game_object) => *game_object = new,
+            // spacecraft changes never target other objects
+            _ => {}
+        }
+    }
+}
+
+/// Difference between two states of a game, turns the baseline frame into `sync.frame`
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GameDelta {
+    pub baseline_frame: usize,
+    pub sync: GameSync,
+    pub time_elapsed: f32,
+    pub players: Option<HashMap<PlayerId, Player>>,
+    pub component_catalogue: Option<ComponentCatalogue>,
+    /// Messages appended to the log since the baseline
+    pub log: Vec<String>,
+    pub seed: Option<u64>,
+    pub(super) rng: ChaChaRng,
+    pub created: Vec<(GameObjectId, GameObject)>,
+    pub removed: Vec<GameObjectId>,
+    pub changed: Vec<(GameObjectId, Vec<GameObjectChange>)>,
+}
+
+impl GameDelta {
+    pub fn new(baseline: &Game, game: &Game) -> Self {
+        let players_hash =
+            |game: &Game| stable_hash(&game.players.iter().collect::<BTreeMap<_, _>>());
+        let players = (players_hash(baseline) != players_hash(game)).then(|| game.players.clone());
+        let component_catalogue = (stable_hash(&baseline.component_catalogue)
+            != stable_hash(&game.component_catalogue))
+        .then(|| game.component_catalogue.clone());
+
+        let mut created = vec![];
+        let mut changed = vec![];
+        for id in game.sorted_game_object_ids() {
+            let game_object = &game.game_objects[&id];
+            match baseline.game_objects.get(&id) {
+                Some(old) => {
+                    let changes = GameObjectChange::between(old, game_object);
+                    if !changes.is_empty() {
+                        changed.push((id, changes));
+                    }
+                }
+                None => created.push((id, game_object.clone())),
+            }
+        }
+        let mut removed = baseline
+            .game_objects
+            .keys()
+            .filter(|id| !game.game_objects.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        removed.sort();
+
+        Self {
+            baseline_frame: baseline.sync.frame,
+            sync: game.sync.clone(),
+            time_elapsed: game.time_elapsed,
+            players,
+            component_catalogue,
+            log: game.log.iter().skip(baseline.log.len()).cloned().collect(),
+            seed: game.seed,
+            rng: game.rng.clone(),
+            created,
+            removed,
+            changed,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DeltaError {
+    BaselineMismatch(usize, usize), // own frame, delta baseline frame
+    InvalidId(GameObjectId),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    #[test]
+    fn delta_roundtrip_sends_only_changed_components() {
+        let mut game = Game::with_seed(5);
+        game.execute_cmd(User::Server, GameCmd::AddPlayer(1))
+            .unwrap();
+        for i in 0..10 {
+            let cmd = GameCmd::SpawnRandomAsteroid(vec2(i as f32 * 30., 50.), vec2(0., i as f32));
+            game.execute_cmd(User::Server, cmd).unwrap();
+        }
+        let spacecraft = SpacecraftBuilder::new()
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(-2, 0),
+                Orientation::Right,
+            )
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, 1), Orientation::Right)
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, -1), Orientation::Right)
+            .at(vec2(0., -50.), Vec2::ZERO, 0.)
+            .owner(1)
+            .build();
+        game.handle_game_object_effect(GameObjectEffect::SpawnSpacecraft(spacecraft));
+        let spacecraft_id = *game
+            .sorted_game_object_ids()
+            .iter()
+            .find(|id| matches!(game.game_objects[id], GameObject::Spacecraft(_)))
+            .unwrap();
+        game.update(0.05);
+        game.save_snapshot();
+        let mut client: Game = deserialize_bytes(&serialize_bytes(&game).unwrap()).unwrap();
+        let baseline_frame = game.sync.frame;
+
+        let cmd = GameCmd::ExecuteComponentCmd(spacecraft_id, 1, ComponentCmd::SetActive(true));
+        game.execute_cmd(User::Server, cmd).unwrap();
+        let cmd = GameCmd::SpawnStarBase(1, vec2(0., 300.), Vec2::ZERO);
+        game.execute_cmd(User::Server, cmd).unwrap();
+        for _ in 0..10 {
+            game.update(0.05);
+        }
+
+        let delta = game.diff(baseline_frame).unwrap();
+        let (_, changes) = delta
+            .changed
+            .iter()
+            .find(|(id, _)| *id == spacecraft_id)
+            .unwrap();
+        assert!(changes.iter().all(|change| match change {
+            GameObjectChange::Components(components) => components.len() == 1,
+            change => !matches!(change, GameObjectChange::Replace(_)),
+        }));
+
+        let delta: GameDelta = deserialize_bytes(&serialize_bytes(&delta).unwrap()).unwrap();
+        client.apply_delta(delta).unwrap();
+        assert_eq!(client.state_hash(), game.state_hash());
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+/// Fraction of the blast which gets through every object standing in its way
+pub const OCCLUSION_ATTENUATION: f32 = 0.5;
+
+/// Explosive charge of a projectile
+#[derive(Clone, Serialize, Deserialize, Debug, Copy)]
+pub struct Warhead {
+    pub radius: f32,
+    /// Damage at the center of the explosion, decreases linearly to zero at `radius`
+    pub damage: f32,
+    /// Impulse at the center of the explosion, decreases the same way as the damage
+    pub impulse: f32,
+    /// Distance from an enemy object at which the warhead detonates, zero disables the fuse
+    pub proximity: f32,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Explosion {
+    pub position: Vec2,
+    pub time: f32,
+    pub radius: f32,
+    pub damage: f32,
+    pub impulse: f32,
+    /// Receives the materials salvaged by the explosion
+    pub owner: Option<PlayerId>,
+}
+
+impl Explosion {
+    pub fn new(warhead: &Warhead, position: Vec2, time: f32, owner: Option<PlayerId>) -> Self {
+        Self {
+            position,
+            time,
+            radius: warhead.radius,
+            damage: warhead.damage,
+            impulse: warhead.impulse,
+            owner,
+        }
+    }
+    /// Share of the blast reaching `point`, `occluders` is the number of objects in the way
+    pub fn attenuation(&self, point: Vec2, occluders: usize) -> f32 {
+        (1. - point.distance(self.position) / self.radius).max(0.)
+            * OCCLUSION_ATTENUATION.powi(occluders as i32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blast_is_weakened_by_objects_in_the_way() {
+        let mut game = Game::with_seed(0);
+        for x in [5., 10.] {
+            let asteroid = Asteroid::new(
+                vec2(x, 0.),
+                Vec2::ZERO,
+                0.,
+                1.5,
+                Material::Iron,
+                &mut game.rng,
+            );
+            game.handle_game_object_effect(GameObjectEffect::SpawnAsteroid(asteroid));
+        }
+        let warhead = Warhead {
+            radius: 20.,
+            damage: 10.,
+            impulse: 1000.,
+            proximity: 0.,
+        };
+        game.handle_game_object_effect(GameObjectEffect::Explosion(Explosion::new(
+            &warhead,
+            Vec2::ZERO,
+            0.,
+            None,
+        )));
+
+        let mut asteroids = game.asteroids();
+        asteroids.sort_by(|a, b| a.body.position.x.total_cmp(&b.body.position.x));
+        let (near, far) = (asteroids[0], asteroids[1]);
+        assert!(near.body.velocity.x > 0. && far.body.velocity.x > 0.);
+        assert!(near.body.velocity.x > 2. * far.body.velocity.x);
+        assert!(matches!(game.events[..], [GameEvent::Explosion(_)]));
+    }
+}
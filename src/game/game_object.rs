@@ -1,12 +1,14 @@
 use super::*;
 
-#[derive(Clone, Serialize, Deserialize, Debug, IntoStaticStr)]
+#[derive(Clone, Serialize, Deserialize, Debug, IntoStaticStr, EnumDiscriminants)]
+#[strum_discriminants(name(GameObjectKind), derive(Serialize, Deserialize, Hash))]
 // #[serde(tag = "type")]
 pub enum GameObject {
     Asteroid(Asteroid),
     StarBase(StarBase),
     Spacecraft(Spacecraft),
     Projectile(Projectile),
+    Wreck(Wreck),
 }
 
 impl GameObject {
@@ -16,6 +18,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => &mut star_base.body,
             GameObject::Spacecraft(spacecraft) => &mut spacecraft.body,
             GameObject::Projectile(projectile) => &mut projectile.body,
+            GameObject::Wreck(wreck) => &mut wreck.body,
         }
     }
     pub fn body(&self) -> &GameObjectBody {
@@ -24,6 +27,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => &star_base.body,
             GameObject::Spacecraft(spacecraft) => &spacecraft.body,
             GameObject::Projectile(projectile) => &projectile.body,
+            GameObject::Wreck(wreck) => &wreck.body,
         }
     }
     pub fn mass(&self) -> f32 {
@@ -32,6 +36,16 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.mass(),
             GameObject::Spacecraft(spacecraft) => spacecraft.mass,
             GameObject::Projectile(projectile) => projectile.mass,
+            GameObject::Wreck(wreck) => wreck.mass(),
+        }
+    }
+    pub fn inertia(&self) -> f32 {
+        match self {
+            GameObject::Asteroid(asteroid) => asteroid.inertia(),
+            GameObject::StarBase(star_base) => star_base.inertia(),
+            GameObject::Spacecraft(spacecraft) => spacecraft.inertia,
+            GameObject::Projectile(projectile) => projectile.inertia(),
+            GameObject::Wreck(wreck) => wreck.inertia,
         }
     }
     pub fn destroyed(&self) -> bool {
@@ -40,6 +54,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.destroyed(),
             GameObject::Spacecraft(spacecraft) => spacecraft.destroyed(),
             GameObject::Projectile(projectile) => projectile.destroyed(),
+            GameObject::Wreck(wreck) => wreck.destroyed(),
         }
     }
     pub fn owner(&self) -> Option<PlayerId> {
@@ -48,6 +63,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.owner(),
             GameObject::Spacecraft(spacecraft) => spacecraft.owner(),
             GameObject::Projectile(projectile) => projectile.owner(),
+            GameObject::Wreck(wreck) => wreck.owner(),
         }
     }
     pub fn collides_point(&self, position: Vec2) -> bool {
@@ -75,6 +91,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.health(),
             GameObject::Spacecraft(spacecraft) => spacecraft.health(),
             GameObject::Projectile(projectile) => projectile.health(),
+            GameObject::Wreck(wreck) => wreck.health(),
         }
     }
     pub fn bounciness(&self) -> f32 {
@@ -83,6 +100,17 @@ impl GameObject {
             GameObject::StarBase(_star_base) => 0.7,
             GameObject::Spacecraft(_spacecraft) => 0.4,
             GameObject::Projectile(_projectile) => 0.1,
+            GameObject::Wreck(_wreck) => 0.4,
+        }
+    }
+    /// Coulomb friction coefficient, the coefficients of both objects are combined in collisions
+    pub fn friction(&self) -> f32 {
+        match self {
+            GameObject::Asteroid(_asteroid) => 0.6,
+            GameObject::StarBase(_star_base) => 0.5,
+            GameObject::Spacecraft(_spacecraft) => 0.4,
+            GameObject::Projectile(_projectile) => 0.2,
+            GameObject::Wreck(_wreck) => 0.4,
         }
     }
     pub fn destructive_power(&self) -> f32 {
@@ -97,6 +125,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.apply_damage(damage, position),
             GameObject::Spacecraft(spacecraft) => spacecraft.apply_damage(damage, position),
             GameObject::Projectile(projectile) => projectile.apply_damage(damage, position),
+            GameObject::Wreck(wreck) => wreck.apply_damage(damage, position),
         }
     }
     pub fn collides(&self, other: &GameObject) -> bool {
@@ -123,6 +152,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.update(time),
             GameObject::Spacecraft(spacecraft) => spacecraft.update(time),
             GameObject::Projectile(projectile) => projectile.update(time),
+            GameObject::Wreck(wreck) => wreck.update(time),
         };
         self.body_mut().update(time);
         result
@@ -137,6 +167,7 @@ impl GameObject {
             GameObject::StarBase(star_base) => star_base.update(time),
             GameObject::Spacecraft(spacecraft) => spacecraft.update(time),
             GameObject::Projectile(projectile) => projectile.update(time),
+            GameObject::Wreck(wreck) => wreck.update(time),
         };
         self.body_mut().update_fixed(time);
         result
@@ -146,6 +177,12 @@ impl GameObject {
 pub enum GameObjectEffect {
     LaunchProjectile(Projectile),
     SpawnSpacecraft(Spacecraft),
+    /// Structure finished in a hangar, built from the game's component catalogue
+    BuildSpacecraft(SpacecraftStructure, PlayerId, GameObjectBody),
+    SpawnWreck(Wreck),
+    SpawnAsteroid(Asteroid),
+    Explosion(Explosion),
+    FireBeam(Vec2, Vec2, f32, f32, PlayerId), // origin, direction, range, damage, owner
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq)]
@@ -212,6 +249,7 @@ impl GameObjectBody {
         let dt = time - self.cur_time;
 
         self.position += self.velocity * dt;
+        self.rotation = (self.rotation + self.angular_velocity * dt) % (PI * 2.);
         self.velocity += self.acceleration * dt;
 
         self.angular_velocity += self.angular_acceleration * dt;
@@ -221,9 +259,38 @@ impl GameObjectBody {
         self.cur_time = time;
         self.updated += 1;
     }
+    /// Position and rotation at `time` assuming constant velocities, the same motion
+    /// `update_fixed` performs but without modifying the body
+    pub fn predicted(&self, time: f32) -> Self {
+        let dt = time - self.cur_time;
+        Self {
+            position: self.position + self.velocity * dt,
+            rotation: (self.rotation + self.angular_velocity * dt) % (PI * 2.),
+            cur_time: time,
+            ..self.clone()
+        }
+    }
+    /// Distance of the furthest bound from the center of rotation
+    pub fn radius(&self) -> f32 {
+        self.bounds.iter().map(|p| p.length()).fold(0., f32::max)
+    }
+    /// Velocity of a point given in world coordinates, including the rotation of the body
+    pub fn point_velocity(&self, world_pos: Vec2) -> Vec2 {
+        self.velocity + (world_pos - self.position).perp() * self.angular_velocity
+    }
+    pub fn world_bounds(&self) -> Vec<Vec2> {
+        self.bounds
+            .iter()
+            .map(|p| self.relative_to_world(*p))
+            .collect()
+    }
     pub fn relative_to_world(&self, relative_pos: Vec2) -> Vec2 {
         relative_pos.rotate_rad(self.rotation) + self.position
     }
+    /// Inverse of `relative_to_world`
+    pub fn world_to_relative(&self, world_pos: Vec2) -> Vec2 {
+        (world_pos - self.position).rotate_rad(-self.rotation)
+    }
     pub fn point_position(&self, index: usize) -> Vec2 {
         self.relative_to_world(self.bounds[index])
     }
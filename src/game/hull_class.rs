@@ -0,0 +1,54 @@
+use crate::prelude::*;
+use crate::CONSTRUCTION_SIZE;
+
+/// Size class of a spacecraft, bounds its structure and decides which hangars can build it
+#[derive(
+    Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub enum HullClass {
+    #[default]
+    Fighter,
+    Frigate,
+    Cruiser,
+}
+
+impl HullClass {
+    /// Largest absolute coordinate of a cell the structure can occupy
+    pub fn grid_size(self) -> i32 {
+        match self {
+            HullClass::Fighter => 4,
+            HullClass::Frigate => 8,
+            HullClass::Cruiser => (CONSTRUCTION_SIZE as i32 - 1) / 2,
+        }
+    }
+    /// Most components of the type the structure can have, None if there is no limit
+    pub fn max_components(self, component_type: ComponentType) -> Option<usize> {
+        let weapon = matches!(
+            component_type,
+            ComponentType::KINETIC_WEAPON
+                | ComponentType::MISSILE_LAUNCHER
+                | ComponentType::LASER_WEAPON
+        );
+        match (self, component_type) {
+            (HullClass::Fighter, _) if weapon => Some(2),
+            (HullClass::Fighter, ComponentType::REACTOR | ComponentType::SHIELD_GENERATOR) => {
+                Some(1)
+            }
+            (HullClass::Frigate, _) if weapon => Some(6),
+            (HullClass::Frigate, ComponentType::REACTOR | ComponentType::SHIELD_GENERATOR) => {
+                Some(2)
+            }
+            (HullClass::Cruiser, _) if weapon => Some(16),
+            (HullClass::Cruiser, ComponentType::SHIELD_GENERATOR) => Some(4),
+            _ => None,
+        }
+    }
+    /// Smallest hangar which can build the class
+    pub fn hangar_size(self) -> u32 {
+        match self {
+            HullClass::Fighter => 1,
+            HullClass::Frigate => 2,
+            HullClass::Cruiser => 3,
+        }
+    }
+}
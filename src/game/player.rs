@@ -1,17 +1,22 @@
 use crate::prelude::*;
 
+use super::blueprint::{Blueprint, BlueprintId};
+
 pub type PlayerId = u64;
 
 #[derive(Default, Clone, Serialize, Deserialize, Debug)]
 pub struct Player {
     pub materials: BTreeMap<Material, f32>,
+    #[serde(default)]
+    pub blueprints: BTreeMap<BlueprintId, Blueprint>,
+    /// Id of the next saved blueprint, ids of deleted blueprints aren't reused
+    #[serde(default)]
+    next_blueprint_id: BlueprintId,
 }
 
 impl Player {
     pub fn new() -> Self {
-        let materials = BTreeMap::new();
-
-        Self { materials }
+        Self::default()
     }
     pub fn take_materials(&mut self, materials: &BTreeMap<Material, f32>) -> bool {
         if self.has_materials(materials) {
@@ -35,4 +40,27 @@ impl Player {
             *self.materials.entry(material).or_default() += amount;
         }
     }
+    pub fn save_blueprint(&mut self, blueprint: Blueprint) -> BlueprintId {
+        let id = self.next_blueprint_id;
+        self.next_blueprint_id += 1;
+        self.blueprints.insert(id, blueprint);
+        id
+    }
+    pub fn rename_blueprint(&mut self, id: BlueprintId, name: String) -> bool {
+        let Some(blueprint) = self.blueprints.get_mut(&id) else {
+            return false;
+        };
+        blueprint.name = name;
+        true
+    }
+    pub fn delete_blueprint(&mut self, id: BlueprintId) -> Option<Blueprint> {
+        self.blueprints.remove(&id)
+    }
+    /// Ids and names of the saved blueprints, ordered by id
+    pub fn list_blueprints(&self) -> Vec<(BlueprintId, &str)> {
+        self.blueprints
+            .iter()
+            .map(|(id, blueprint)| (*id, blueprint.name.as_str()))
+            .collect()
+    }
 }
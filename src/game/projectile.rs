@@ -1,5 +1,10 @@
 use crate::prelude::*;
 
+use super::{Explosion, Warhead};
+
+/// Angular velocity per radian between the rotation of a guided projectile and its desired one
+const STEERING_GAIN: f32 = 8.;
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Projectile {
     pub body: GameObjectBody,
@@ -10,20 +15,114 @@ pub struct Projectile {
     pub lifetime: f32,
     pub origin: ProjectileType,
     pub destructive_power: f32,
+    #[serde(default)]
+    pub guidance: Option<Guidance>,
+    /// Explosive projectiles detonate on contact, close to enemies and when their lifetime ends
+    #[serde(default)]
+    pub warhead: Option<Warhead>,
+}
+
+/// What a weapon or a guided projectile aims at
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq)]
+pub enum Target {
+    GameObject(GameObjectId),
+    Point(Vec2),
+}
+
+/// Steering of a guided projectile, it thrusts along its rotation and turns towards the target
+/// using proportional navigation
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Guidance {
+    pub target: Option<Target>,
+    /// Acceleration along the rotation of the projectile
+    pub thrust: f32,
+    /// Maximal angular velocity
+    pub turn_rate: f32,
+    pub navigation_constant: f32,
+    /// Last known position and velocity of the target, refreshed by the game every update
+    pub target_position: Option<Vec2>,
+    pub target_velocity: Vec2,
+}
+
+impl Guidance {
+    /// Angular velocity turning `body` towards the acceleration commanded by proportional
+    /// navigation, the lateral acceleration is the rotation of the line of sight multiplied by
+    /// the closing speed and the navigation constant. When the target isn't getting closer
+    /// the projectile turns straight at it.
+    pub fn steer(&self, body: &GameObjectBody) -> f32 {
+        let Some(target_position) = self.target_position else {
+            return 0.;
+        };
+        let line_of_sight = target_position - body.position;
+        let distance = line_of_sight.length();
+        if distance < 1e-3 {
+            return 0.;
+        }
+        let relative_velocity = self.target_velocity - body.velocity;
+        let closing_speed = -line_of_sight.dot(relative_velocity) / distance;
+        let line_of_sight_rate = line_of_sight.perp_dot(relative_velocity) / (distance * distance);
+
+        let mut desired = line_of_sight / distance * self.thrust;
+        if closing_speed > 0. {
+            let lateral = self.navigation_constant * closing_speed * line_of_sight_rate;
+            desired += body.velocity.perp().normalize_or_zero() * lateral;
+        }
+        (normalize_radians(desired.angle() - body.rotation) * STEERING_GAIN)
+            .clamp(-self.turn_rate, self.turn_rate)
+    }
 }
 
 impl Projectile {
     pub fn update(&mut self, time: f32) -> Vec<GameObjectEffect> {
         self.lifetime -= time - self.body.cur_time;
 
+        if self.lifetime <= 0. {
+            return self
+                .detonate()
+                .map(GameObjectEffect::Explosion)
+                .into_iter()
+                .collect();
+        }
+
+        if let Some(guidance) = &self.guidance {
+            self.body.angular_velocity = guidance.steer(&self.body);
+            self.body.acceleration += Vec2::from_angle(self.body.rotation) * guidance.thrust;
+        }
+
         vec![]
     }
+    /// Destroys the projectile, explosive ones turn into an explosion
+    pub fn detonate(&mut self) -> Option<Explosion> {
+        self.health = 0.;
+        self.warhead.take().map(|warhead| {
+            Explosion::new(
+                &warhead,
+                self.body.position,
+                self.body.cur_time,
+                Some(self.owner),
+            )
+        })
+    }
+    /// Guided projectiles start homing at `target`, unguided ones ignore it
+    pub fn with_target(mut self, target: Option<Target>) -> Self {
+        if let Some(guidance) = &mut self.guidance {
+            guidance.target = target;
+            if let Some(Target::Point(point)) = target {
+                guidance.target_position = Some(point);
+            }
+        }
+        self
+    }
 }
 
 impl Projectile {
     pub fn mass(&self) -> f32 {
         self.mass
     }
+    /// Moment of inertia of a rectangle
+    pub fn inertia(&self) -> f32 {
+        self.mass * self.size.length_squared() / 12.
+    }
 
     pub fn destroyed(&self) -> bool {
         self.health <= 0.
@@ -55,6 +154,8 @@ impl Projectile {
 pub enum ProjectileType {
     Bullet,
     Missile,
+    /// Unguided explosive with a proximity fuse
+    Shell,
 }
 
 impl ProjectileType {
@@ -69,6 +170,33 @@ impl ProjectileType {
         let (health, mass, scale, lifetime, destructive_power) = match &self {
             ProjectileType::Bullet => (50., 10., vec2(0.2, 0.2), 10., 0.03),
             ProjectileType::Missile => (500., 30., vec2(0.3, 0.3), 20., 0.1),
+            ProjectileType::Shell => (50., 15., vec2(0.25, 0.25), 6., 0.03),
+        };
+        let guidance = match &self {
+            ProjectileType::Bullet | ProjectileType::Shell => None,
+            ProjectileType::Missile => Some(Guidance {
+                target: None,
+                thrust: 15.,
+                turn_rate: 2.5,
+                navigation_constant: 4.,
+                target_position: None,
+                target_velocity: Vec2::ZERO,
+            }),
+        };
+        let warhead = match &self {
+            ProjectileType::Bullet => None,
+            ProjectileType::Missile => Some(Warhead {
+                radius: 10.,
+                damage: 40.,
+                impulse: 2000.,
+                proximity: 2.,
+            }),
+            ProjectileType::Shell => Some(Warhead {
+                radius: 6.,
+                damage: 25.,
+                impulse: 800.,
+                proximity: 3.,
+            }),
         };
 
         let bounds = vec![
@@ -89,6 +217,35 @@ impl ProjectileType {
             size: scale,
             origin: *self,
             destructive_power,
+            guidance,
+            warhead,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missile_intercepts_moving_target() {
+        let mut target = GameObjectBody::new(vec2(150., 60.), vec2(-4., -6.), 0., 0., vec![]);
+        let mut missile = ProjectileType::Missile
+            .construct(Vec2::ZERO, vec2(30., 0.), 0., 0., 0)
+            .with_target(Some(Target::GameObject(1)));
+
+        let mut closest = f32::MAX;
+        let mut time = 0.;
+        while missile.lifetime > 0. {
+            time += 0.02;
+            let guidance = missile.guidance.as_mut().unwrap();
+            guidance.target_position = Some(target.position);
+            guidance.target_velocity = target.velocity;
+            missile.update(time);
+            missile.body.update(time);
+            target.update(time);
+            closest = closest.min(missile.body.position.distance(target.position));
         }
+        assert!(closest < 1., "missed by {}", closest);
     }
 }
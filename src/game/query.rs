@@ -0,0 +1,211 @@
+use super::*;
+
+use self::collision_detection::{point_in_convex_polygon, sat_collision_detect};
+
+/// Which owners a spatial query accepts
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Default)]
+pub enum OwnerFilter {
+    #[default]
+    Any,
+    /// Only objects of this player
+    Only(PlayerId),
+    /// Everything except the objects of this player, objects without an owner included
+    Except(PlayerId),
+}
+
+/// Restricts the game objects returned by spatial queries, the default accepts everything.
+/// Objects destroyed during the current update are never returned.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct QueryFilter {
+    pub owner: OwnerFilter,
+    /// Accepted variants, None accepts all of them
+    pub kinds: Option<Vec<GameObjectKind>>,
+}
+
+impl QueryFilter {
+    pub fn with_owner(mut self, owner: OwnerFilter) -> Self {
+        self.owner = owner;
+        self
+    }
+    pub fn with_kinds(mut self, kinds: &[GameObjectKind]) -> Self {
+        self.kinds = Some(kinds.to_vec());
+        self
+    }
+    pub fn accepts(&self, game_object: &GameObject) -> bool {
+        let owner = game_object.owner();
+        let owner_accepted = match self.owner {
+            OwnerFilter::Any => true,
+            OwnerFilter::Only(player_id) => owner == Some(player_id),
+            OwnerFilter::Except(player_id) => owner != Some(player_id),
+        };
+        owner_accepted
+            && self
+                .kinds
+                .as_ref()
+                .is_none_or(|kinds| kinds.contains(&GameObjectKind::from(game_object)))
+            && game_object.health() > 0.
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq)]
+pub struct RaycastHit {
+    pub id: GameObjectId,
+    pub point: Vec2,
+    /// Normal of the hit edge pointing against the ray, zero if the ray starts inside the object
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+impl Game {
+    /// First object hit by the ray from `origin` along `direction` within `max_distance`
+    pub fn raycast(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<RaycastHit> {
+        let direction = direction.try_normalize()?;
+        let area = Aabb::from_points([origin, origin + direction * max_distance])?;
+
+        let mut result: Option<RaycastHit> = None;
+        for id in self.objects_in_rect(&area) {
+            let game_object = &self.game_objects[&id];
+            if !filter.accepts(game_object) {
+                continue;
+            }
+            let Some((distance, normal)) =
+                ray_cast_polygon(origin, direction, &game_object.body().world_bounds())
+            else {
+                continue;
+            };
+            if distance <= max_distance && result.is_none_or(|hit| distance < hit.distance) {
+                result = Some(RaycastHit {
+                    id,
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                });
+            }
+        }
+        result
+    }
+    /// Objects containing `point`, ordered by id
+    pub fn query_point(&self, point: Vec2, filter: &QueryFilter) -> Vec<GameObjectId> {
+        self.objects_in_rect(&Aabb::new(point, point))
+            .into_iter()
+            .filter(|id| {
+                let game_object = &self.game_objects[id];
+                filter.accepts(game_object)
+                    && point_in_convex_polygon(point, &game_object.body().world_bounds())
+            })
+            .collect()
+    }
+    /// Objects overlapping the circle, ordered by id
+    pub fn query_circle(
+        &self,
+        center: Vec2,
+        radius: f32,
+        filter: &QueryFilter,
+    ) -> Vec<GameObjectId> {
+        self.objects_within_radius(center, radius)
+            .into_iter()
+            .filter(|id| {
+                let game_object = &self.game_objects[id];
+                filter.accepts(game_object)
+                    && closest_point_on_polygon(center, &game_object.body().world_bounds())
+                        .is_some_and(|point| point.distance(center) <= radius)
+            })
+            .collect()
+    }
+    /// Objects overlapping the convex `polygon` given in world coordinates, ordered by id
+    pub fn query_polygon(&self, polygon: &[Vec2], filter: &QueryFilter) -> Vec<GameObjectId> {
+        let Some(area) = Aabb::from_points(polygon.iter().copied()) else {
+            return vec![];
+        };
+        let polygon = polygon.to_vec();
+        self.objects_in_rect(&area)
+            .into_iter()
+            .filter(|id| {
+                let game_object = &self.game_objects[id];
+                filter.accepts(game_object)
+                    && sat_collision_detect(&game_object.body().world_bounds(), &polygon)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_respect_filters() {
+        let mut game = Game::with_seed(0);
+        for x in [0., 10., 20.] {
+            let asteroid = Asteroid::new(
+                vec2(x, 0.),
+                Vec2::ZERO,
+                0.,
+                2.,
+                Material::Iron,
+                &mut game.rng,
+            );
+            game.handle_game_object_effect(GameObjectEffect::SpawnAsteroid(asteroid));
+        }
+        let projectile = ProjectileType::Bullet.construct(vec2(5., 0.), Vec2::ZERO, 0., 0., 1);
+        game.handle_game_object_effect(GameObjectEffect::LaunchProjectile(projectile));
+        let position = |id: &GameObjectId| game.game_objects[id].body().position;
+
+        let all = QueryFilter::default();
+        let asteroids = QueryFilter::default().with_kinds(&[GameObjectKind::Asteroid]);
+
+        let hit = game.raycast(vec2(-10., 0.), Vec2::X, 100., &all).unwrap();
+        assert_eq!(position(&hit.id), vec2(0., 0.));
+        assert!(hit.normal.x < 0. && (-2.3..-1.7).contains(&hit.point.x));
+        assert_eq!(game.raycast(vec2(-10., 0.), Vec2::X, 5., &all), None);
+
+        let hit = game.raycast(vec2(3., 0.), Vec2::X, 100., &all).unwrap();
+        assert!(matches!(
+            game.game_objects[&hit.id],
+            GameObject::Projectile(_)
+        ));
+        let hit = game
+            .raycast(vec2(3., 0.), Vec2::X, 100., &asteroids)
+            .unwrap();
+        assert_eq!(position(&hit.id), vec2(10., 0.));
+        let not_owned = QueryFilter::default().with_owner(OwnerFilter::Except(1));
+        let hit = game
+            .raycast(vec2(3., 0.), Vec2::X, 100., &not_owned)
+            .unwrap();
+        assert_eq!(position(&hit.id), vec2(10., 0.));
+
+        assert_eq!(game.query_point(vec2(20.5, 0.5), &all).len(), 1);
+        assert!(game.query_point(vec2(15., 0.), &all).is_empty());
+        assert_eq!(game.query_circle(vec2(15., 0.), 4., &all).len(), 2);
+        assert_eq!(game.query_circle(vec2(5., 0.), 1., &asteroids).len(), 0);
+        let only_owned = QueryFilter::default().with_owner(OwnerFilter::Only(1));
+        assert_eq!(game.query_circle(vec2(5., 0.), 1., &only_owned).len(), 1);
+
+        let polygon = [vec2(8., -1.), vec2(22., -1.), vec2(22., 1.), vec2(8., 1.)];
+        assert_eq!(game.query_polygon(&polygon, &asteroids).len(), 2);
+
+        // clients index the objects of a full sync and of deltas themselves
+        let mut client: Game = deserialize_bytes(&serialize_bytes(&game).unwrap()).unwrap();
+        assert_eq!(client.query_polygon(&polygon, &asteroids).len(), 2);
+        game.save_snapshot();
+        let asteroid = Asteroid::new(
+            vec2(0., 30.),
+            Vec2::ZERO,
+            0.,
+            2.,
+            Material::Iron,
+            &mut game.rng,
+        );
+        game.handle_game_object_effect(GameObjectEffect::SpawnAsteroid(asteroid));
+        client
+            .apply_delta(game.diff(client.sync.frame).unwrap())
+            .unwrap();
+        assert_eq!(client.query_point(vec2(0., 30.), &all).len(), 1);
+    }
+}
@@ -0,0 +1,201 @@
+use super::*;
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ReplayHeader {
+    pub version: String,
+    pub seed: Option<u64>,
+    pub initial_state: Game,
+}
+
+/// A recorded match: the initial snapshot followed by every executed command and update.
+/// Commands are stamped with the frame they were executed in, `dts[i]` is the duration of the
+/// update which advanced the game from frame `initial_state.sync.frame + i` to the next one.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub cmds: Vec<ExecutedGameCmd>,
+    pub dts: Vec<f32>,
+    pub final_hash: u64,
+}
+
+impl Replay {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, std::io::Error> {
+        serialize_bytes(self)
+    }
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ReplayError> {
+        let replay: Replay = deserialize_bytes(data).map_err(|_| ReplayError::InvalidFormat)?;
+        if replay.header.version != VERSION {
+            return Err(ReplayError::VersionMismatch(replay.header.version));
+        }
+        Ok(replay)
+    }
+    pub fn first_frame(&self) -> usize {
+        self.header.initial_state.sync.frame
+    }
+    pub fn last_frame(&self) -> usize {
+        self.first_frame() + self.dts.len()
+    }
+}
+
+/// Remembers where the recording of a game started, the game itself keeps the history.
+/// The game only keeps the update durations while a recorder is attached, so every started
+/// recorder has to be finished.
+pub struct ReplayRecorder {
+    initial_state: Game,
+    cmds_offset: usize,
+    dts_offset: usize,
+}
+
+impl ReplayRecorder {
+    pub fn start(game: &mut Game) -> Self {
+        game.recorders += 1;
+        Self {
+            initial_state: game.snapshot(),
+            cmds_offset: game.cmds_history.len(),
+            dts_offset: game.dt_history.len(),
+        }
+    }
+    pub fn finish(self, game: &mut Game) -> Replay {
+        let replay = Replay {
+            header: ReplayHeader {
+                version: VERSION.to_string(),
+                seed: self.initial_state.seed,
+                initial_state: self.initial_state,
+            },
+            cmds: game.cmds_history[self.cmds_offset..].to_vec(),
+            dts: game.dt_history[self.dts_offset..].to_vec(),
+            final_hash: game.state_hash(),
+        };
+        game.recorders -= 1;
+        if game.recorders == 0 {
+            game.dt_history.clear();
+        }
+        replay
+    }
+}
+
+/// Re-executes a replay tick by tick
+pub struct ReplayPlayer {
+    replay: Replay,
+    game: Game,
+    next_cmd: usize,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        let game = replay.header.initial_state.clone();
+        Self {
+            replay,
+            game,
+            next_cmd: 0,
+        }
+    }
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+    pub fn frame(&self) -> usize {
+        self.game.sync.frame
+    }
+    pub fn finished(&self) -> bool {
+        self.frame() >= self.replay.last_frame()
+    }
+    fn execute_frame_cmds(&mut self) {
+        while let Some(executed) = self.replay.cmds.get(self.next_cmd) {
+            if executed.frame > self.frame() {
+                break;
+            }
+            // failed commands fail identically during the replay
+            let _ = self.game.execute_cmd(executed.user, executed.cmd.clone());
+            self.next_cmd += 1;
+        }
+    }
+    /// Executes the commands of the current frame and advances the game by one update.
+    /// Returns false if the replay has already ended.
+    pub fn step(&mut self) -> bool {
+        if self.finished() {
+            return false;
+        }
+        self.execute_frame_cmds();
+        let dt = self.replay.dts[self.frame() - self.replay.first_frame()];
+        self.game.update(dt);
+        if self.finished() {
+            self.execute_frame_cmds();
+        }
+        true
+    }
+    /// Moves the playback to `frame`, rewinds to the initial snapshot when seeking backwards
+    pub fn seek(&mut self, frame: usize) {
+        if frame < self.frame() {
+            self.game = self.replay.header.initial_state.clone();
+            self.next_cmd = 0;
+        }
+        while self.frame() < frame && self.step() {}
+    }
+    /// Plays the rest of the replay and checks that it ends in the recorded state
+    pub fn verify(&mut self) -> Result<(), ReplayError> {
+        while self.step() {}
+        self.execute_frame_cmds();
+        let hash = self.game.state_hash();
+        if hash != self.replay.final_hash {
+            return Err(ReplayError::FinalStateMismatch(
+                hash,
+                self.replay.final_hash,
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    InvalidFormat,
+    VersionMismatch(String),
+    FinalStateMismatch(u64, u64), // replayed, recorded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_roundtrip_matches_live_game() {
+        let mut game = Game::with_seed(3);
+        game.execute_cmd(User::Server, GameCmd::AddPlayer(1))
+            .unwrap();
+        for _ in 0..5 {
+            game.update(0.05);
+        }
+        assert!(game.dt_history.is_empty());
+
+        let recorder = ReplayRecorder::start(&mut game);
+        let mut live_hashes = BTreeMap::new();
+        for i in 0..60 {
+            if i < 20 {
+                let cmd =
+                    GameCmd::SpawnRandomAsteroid(vec2(i as f32 * 30., 0.), vec2(0., i as f32));
+                game.execute_cmd(User::Server, cmd).unwrap();
+            }
+            if i == 30 {
+                let cmd = GameCmd::SpawnStarBase(1, vec2(0., 200.), Vec2::ZERO);
+                game.execute_cmd(User::Server, cmd).unwrap();
+            }
+            game.update(if i % 2 == 0 { 0.05 } else { 0.03 });
+            live_hashes.insert(game.sync.frame, game.state_hash());
+        }
+        let replay = recorder.finish(&mut game);
+        assert!(game.dt_history.is_empty());
+
+        let replay = Replay::from_bytes(&replay.to_bytes().unwrap()).unwrap();
+        let mut player = ReplayPlayer::new(replay);
+        for frame in [player.frame() + 40, player.frame() + 10] {
+            player.seek(frame);
+            assert_eq!(player.frame(), frame);
+            assert_eq!(player.game().state_hash(), live_hashes[&frame]);
+        }
+        player.verify().unwrap();
+        assert_eq!(player.game().state_hash(), game.state_hash());
+    }
+}
@@ -1,11 +1,20 @@
+mod capabilities;
 mod component;
+mod flight_controller;
 
 use crate::prelude::*;
+pub use capabilities::{Capabilities, CAPABILITY_DIRECTIONS};
 pub use component::*;
+pub use flight_controller::{FlightController, FlightGoal};
 use std::collections::BTreeMap;
 use std::collections::HashSet;
 
-use super::GameObjectBody;
+use super::{GameObjectBody, Wreck};
+
+/// Distance from the line of penetration within which components are hit
+const DAMAGE_SPREAD: f32 = 0.75;
+/// Fraction of the damage lost per grid cell it penetrates
+const DAMAGE_FALLOFF_PER_CELL: f32 = 0.15;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct Spacecraft {
@@ -18,15 +27,68 @@ pub struct Spacecraft {
     pub mass: f32,
     pub tags: Vec<String>,
     health: f32,
+    /// Fuel tanks every engine draws from, ordered by their distance in the component graph
+    #[serde(skip)]
+    fuel_lines: BTreeMap<ComponentId, Vec<ComponentId>>,
+    #[serde(default)]
+    pub flight_controller: FlightController,
+}
+
+/// Center of mass of the components in grid coordinates
+pub fn components_center_of_mass(components: &BTreeMap<ComponentId, Component>) -> Vec2 {
+    let mut result = Vec2::ZERO;
+    let mut total_mass = 0.;
+    for component in components.values() {
+        result += component.body().centered_position() * component.mass();
+        total_mass += component.mass();
+    }
+    result / total_mass
+}
+
+/// Moment of inertia around `center_of_mass`, components are treated as uniform rectangles of
+/// their scale
+pub fn components_inertia(
+    components: &BTreeMap<ComponentId, Component>,
+    center_of_mass: Vec2,
+) -> f32 {
+    components
+        .values()
+        .map(|x| {
+            x.body()
+                .centered_position()
+                .distance(center_of_mass)
+                .powi(2)
+                * x.mass()
+                + x.body().scale().as_vec2().length_squared() * x.mass() / 12.
+        })
+        .sum()
+}
+
+/// Convex hull of the components relative to `center_of_mass`
+pub fn components_bounds(
+    components: &BTreeMap<ComponentId, Component>,
+    center_of_mass: Vec2,
+) -> Vec<Vec2> {
+    let points = components
+        .values()
+        .map(|x| x.body().corner_points())
+        .flatten()
+        .collect::<Vec<Vec2>>();
+    convex_hull(points)
+        .into_iter()
+        .map(|ver| ver - center_of_mass)
+        .collect()
 }
 
 // needs a lot of caching
 impl Spacecraft {
-    /// Receives a verified structure and builds a spacecraft from it
+    /// Receives a verified structure and builds a spacecraft from it,
+    /// components missing in the catalogue are left out
     pub fn build(
         structure: SpacecraftStructure,
         owner: PlayerId,
         transform: GameObjectBody,
+        catalogue: &ComponentCatalogue,
     ) -> Self {
         let mut components = BTreeMap::new();
         let mut central_component = None;
@@ -39,11 +101,15 @@ impl Spacecraft {
             let component_type = component_placeholder.component_type;
             let orientation = component_placeholder.orientation;
 
-            let component = component_type.build(component_placeholder.position, orientation);
-            if let ComponentType::Central = component_type {
+            let Some(component) =
+                catalogue.build(component_type, component_placeholder.position, orientation)
+            else {
+                continue;
+            };
+            if component_type == ComponentType::CENTRAL {
                 central_component = Some(index as ComponentId);
             }
-            let component_id = (if component_type.top().is_some() {
+            let component_id = (if component.body().top().is_some() {
                 256 + index
             } else {
                 index
@@ -63,9 +129,10 @@ impl Spacecraft {
         spacecraft
     }
     pub fn update(&mut self, time: f32) -> Vec<GameObjectEffect> {
-        self.reconstruct();
+        let mut result = self.reconstruct();
+        self.apply_flight_control();
+        self.distribute_energy(time);
 
-        let mut result = vec![];
         let mut forces = vec![];
         for (_, component) in &mut self.components {
             for component_effect in component.update(time) {
@@ -75,22 +142,35 @@ impl Spacecraft {
                         position,
                         velocity,
                         rotation,
+                        target,
                     ) => {
                         result.push(GameObjectEffect::LaunchProjectile(
-                            projectile_type.construct(
-                                (position - self.center_of_mass).rotate_rad(self.body.rotation)
-                                    + self.body.position,
-                                velocity.rotate_rad(self.body.rotation) + self.body.velocity,
-                                rotation + self.body.rotation,
-                                time,
-                                self.owner,
-                            ),
+                            projectile_type
+                                .construct(
+                                    (position - self.center_of_mass).rotate_rad(self.body.rotation)
+                                        + self.body.position,
+                                    velocity.rotate_rad(self.body.rotation) + self.body.velocity,
+                                    rotation + self.body.rotation,
+                                    time,
+                                    self.owner,
+                                )
+                                .with_target(target),
                         ));
                     }
                     ComponentEffect::ApplyForce(direction) => {
                         let origin = component.body().centered_position();
                         forces.push((origin, direction));
                     }
+                    ComponentEffect::FireBeam(position, rotation, range, damage) => {
+                        result.push(GameObjectEffect::FireBeam(
+                            (position - self.center_of_mass).rotate_rad(self.body.rotation)
+                                + self.body.position,
+                            Vec2::from_angle(rotation + self.body.rotation),
+                            range,
+                            damage,
+                            self.owner,
+                        ));
+                    }
                 }
             }
         }
@@ -99,9 +179,177 @@ impl Spacecraft {
             .into_iter()
             .for_each(|x| self.apply_force_local(x.0, x.1));
 
+        self.distribute_fuel();
+
         result
     }
-    pub fn reconstruct(&mut self) {
+    /// Splits the energy produced by reactors and stored in batteries between the consumers in
+    /// the order of their priority, consumers which don't get their full demand are browned out.
+    /// Surplus production charges the batteries.
+    fn distribute_energy(&mut self, time: f32) {
+        let production = self
+            .components
+            .values()
+            .map(|component| match component {
+                Component::Reactor(reactor) => reactor.production(time),
+                _ => 0.,
+            })
+            .sum::<f32>();
+
+        let mut demands = self
+            .components
+            .iter()
+            .filter_map(|(id, component)| {
+                component
+                    .energy_demand(time)
+                    .map(|(priority, demand)| (priority, *id, demand))
+            })
+            .collect::<Vec<_>>();
+        demands.sort_by_key(|(priority, id, _)| (*priority, *id));
+
+        let mut available = production + self.energy();
+        let mut used = 0.;
+        for (_, id, demand) in demands {
+            let supplied = demand.min(available).max(0.);
+            available -= supplied;
+            used += supplied;
+            if let Some(component) = self.components.get_mut(&id) {
+                component.supply_energy(supplied);
+            }
+        }
+
+        let mut balance = production - used;
+        for component in self.components.values_mut() {
+            if let Component::Battery(battery) = component {
+                if balance >= 0. {
+                    balance -= battery.charge(balance);
+                } else {
+                    balance += battery.discharge(-balance);
+                }
+            }
+        }
+    }
+    /// Energy stored in the batteries
+    pub fn energy(&self) -> f32 {
+        self.components
+            .values()
+            .map(|component| match component {
+                Component::Battery(battery) => battery.energy,
+                _ => 0.,
+            })
+            .sum()
+    }
+    pub fn energy_capacity(&self) -> f32 {
+        self.components
+            .values()
+            .map(|component| match component {
+                Component::Battery(battery) => battery.capacity,
+                _ => 0.,
+            })
+            .sum()
+    }
+    /// Tops up the reserves of the engines from the tanks on their fuel lines, closest first
+    fn distribute_fuel(&mut self) {
+        for (engine_id, tank_ids) in &self.fuel_lines {
+            let Some(Component::Engine(engine)) = self.components.get(engine_id) else {
+                continue;
+            };
+            let mut missing = engine.fuel_capacity - engine.fuel;
+            let mut drawn = 0.;
+            for tank_id in tank_ids {
+                if missing <= 0. {
+                    break;
+                }
+                if let Some(Component::FuelTank(tank)) = self.components.get_mut(tank_id) {
+                    let taken = tank.take_fuel(missing);
+                    missing -= taken;
+                    drawn += taken;
+                }
+            }
+            if let Some(Component::Engine(engine)) = self.components.get_mut(engine_id) {
+                engine.refuel(drawn);
+            }
+        }
+    }
+    /// Pumps up to `amount` of fuel into the tanks and then the engine reserves,
+    /// returns how much was accepted
+    pub fn refuel(&mut self, amount: f32) -> f32 {
+        let mut remaining = amount;
+        for component in self.components.values_mut() {
+            if let Component::FuelTank(tank) = component {
+                remaining -= tank.refuel(remaining);
+            }
+        }
+        for component in self.components.values_mut() {
+            if let Component::Engine(engine) = component {
+                remaining -= engine.refuel(remaining);
+            }
+        }
+        self.mass = self.compute_mass();
+        amount - remaining
+    }
+    /// Fuel in the tanks and engine reserves
+    pub fn fuel(&self) -> f32 {
+        self.components
+            .values()
+            .map(|component| match component {
+                Component::FuelTank(tank) => tank.fuel,
+                Component::Engine(engine) => engine.fuel,
+                _ => 0.,
+            })
+            .sum()
+    }
+    pub fn fuel_capacity(&self) -> f32 {
+        self.components
+            .values()
+            .map(|component| match component {
+                Component::FuelTank(tank) => tank.capacity,
+                Component::Engine(engine) => engine.fuel_capacity,
+                _ => 0.,
+            })
+            .sum()
+    }
+    /// Connects every engine with the tanks reachable through the bottom components,
+    /// `construction` maps grid positions to the components occupying them
+    fn compute_fuel_lines(&mut self, construction: &BTreeMap<(i32, i32), ComponentId>) {
+        let dirs = [ivec2(0, 1), ivec2(1, 0), ivec2(-1, 0), ivec2(0, -1)];
+        self.fuel_lines.clear();
+        for (engine_id, engine) in &self.components {
+            if !matches!(engine, Component::Engine(_)) {
+                continue;
+            }
+            // breadth first search, the tanks are found in the order of their distance
+            let mut tanks = vec![];
+            let mut visited = HashSet::new();
+            let mut queue = engine
+                .body()
+                .occupied_positions()
+                .into_iter()
+                .collect::<VecDeque<_>>();
+            visited.extend(queue.iter().map(|pos| (pos.x, pos.y)));
+            while let Some(pos) = queue.pop_front() {
+                if let Some(id) = construction.get(&(pos.x, pos.y)) {
+                    if matches!(self.components.get(id), Some(Component::FuelTank(_)))
+                        && !tanks.contains(id)
+                    {
+                        tanks.push(*id);
+                    }
+                }
+                for dir in dirs {
+                    let new_pos = pos + dir;
+                    if construction.contains_key(&(new_pos.x, new_pos.y))
+                        && visited.insert((new_pos.x, new_pos.y))
+                    {
+                        queue.push_back(new_pos);
+                    }
+                }
+            }
+            self.fuel_lines.insert(*engine_id, tanks);
+        }
+    }
+    /// Removes destroyed components and detaches every cluster of components which lost the
+    /// connection to the central component, the clusters are returned as wrecks
+    pub fn reconstruct(&mut self) -> Vec<GameObjectEffect> {
         let _ = self
             .components
             .extract_if(|_, x| x.health() <= 0.)
@@ -112,22 +360,27 @@ impl Spacecraft {
         for (id, component) in &self.components {
             if component.body().top().is_some() {
                 let pos = component.body().position;
-                top_construction.insert((pos.x, pos.y), id);
+                top_construction.insert((pos.x, pos.y), *id);
             } else {
                 for pos in component.body().occupied_positions() {
-                    construction.insert((pos.x, pos.y), id);
+                    construction.insert((pos.x, pos.y), *id);
                 }
             }
         }
 
+        let mut full_construction = construction.clone();
+
         let Some(central_component) = self.components.get(&self.central_component) else {
             self.components.clear();
             self.health = 0.;
             self.body.bounds.clear();
-            return;
+            return vec![];
         };
 
         let mut stack = central_component.body().occupied_positions();
+        for pos in &stack {
+            construction.remove(&(pos.x, pos.y));
+        }
         let dirs = [ivec2(0, 1), ivec2(1, 0), ivec2(-1, 0), ivec2(0, -1)];
 
         let mut survives = HashSet::new();
@@ -137,30 +390,64 @@ impl Spacecraft {
                 let new_pos = pos + dir;
                 if let Some(id) = construction.get(&(new_pos.x, new_pos.y)) {
                     stack.push(new_pos);
-                    survives.insert(**id);
+                    survives.insert(*id);
                     if let Some(top_id) = top_construction.get(&(new_pos.x, new_pos.y)) {
-                        survives.insert(**top_id);
+                        survives.insert(*top_id);
                     }
                 }
                 construction.remove(&(new_pos.x, new_pos.y));
             }
         }
 
-        let _ = self
+        // the remaining positions belong to detached components, group them by connectivity
+        let mut clusters = vec![];
+        while let Some(((x, y), id)) = construction.pop_first() {
+            let mut cluster = HashSet::new();
+            cluster.insert(id);
+            if let Some(top_id) = top_construction.get(&(x, y)) {
+                cluster.insert(*top_id);
+            }
+            let mut stack = vec![ivec2(x, y)];
+            while let Some(pos) = stack.pop() {
+                for dir in dirs {
+                    let new_pos = pos + dir;
+                    if let Some(id) = construction.remove(&(new_pos.x, new_pos.y)) {
+                        stack.push(new_pos);
+                        cluster.insert(id);
+                        if let Some(top_id) = top_construction.get(&(new_pos.x, new_pos.y)) {
+                            cluster.insert(*top_id);
+                        }
+                    }
+                }
+            }
+            clusters.push(cluster);
+        }
+
+        let mut detached = self
             .components
             .extract_if(|id, _| !survives.contains(id))
-            .collect::<Vec<_>>();
-        let points = self
-            .components
-            .values()
-            .map(|x| x.body().corner_points())
-            .flatten()
-            .collect::<Vec<Vec2>>();
-        self.body.bounds = convex_hull(points)
-            .into_iter()
-            .map(|ver| ver - self.center_of_mass)
-            .collect();
-        self.health = self.components.values().map(|x| x.health()).sum();
+            .collect::<BTreeMap<_, _>>();
+        full_construction.retain(|_, id| survives.contains(id));
+        self.compute_fuel_lines(&full_construction);
+        let mut effects = vec![];
+        for cluster in clusters {
+            let components = detached
+                .extract_if(|id, _| cluster.contains(id))
+                .collect::<BTreeMap<_, _>>();
+            effects.push(GameObjectEffect::SpawnWreck(Wreck::new(
+                components,
+                &self.body,
+                self.center_of_mass,
+            )));
+        }
+        // top components whose base was destroyed drift away on their own
+        for (id, component) in detached {
+            effects.push(GameObjectEffect::SpawnWreck(Wreck::new(
+                BTreeMap::from([(id, component)]),
+                &self.body,
+                self.center_of_mass,
+            )));
+        }
 
         // here was a message that it needs fixing i removed it because i didn't find anything...
         let new_center_of_mass_offset = self.center_of_mass() - self.center_of_mass;
@@ -168,8 +455,40 @@ impl Spacecraft {
             Vec2::from_angle(self.body.rotation).rotate(new_center_of_mass_offset);
         self.center_of_mass += new_center_of_mass_offset;
 
+        self.body.bounds = components_bounds(&self.components, self.center_of_mass);
+        self.health = self.components.values().map(|x| x.health()).sum();
+
         self.inertia = self.inertia();
         self.mass = self.compute_mass();
+
+        effects
+    }
+
+    /// Copy of everything except the body and the components, which `GameDelta` sends
+    /// separately
+    pub fn state(&self) -> Spacecraft {
+        Spacecraft {
+            owner: self.owner,
+            components: BTreeMap::new(),
+            body: GameObjectBody::default(),
+            central_component: self.central_component,
+            inertia: self.inertia,
+            center_of_mass: self.center_of_mass,
+            mass: self.mass,
+            tags: self.tags.clone(),
+            health: self.health,
+            fuel_lines: BTreeMap::new(),
+            flight_controller: self.flight_controller.clone(),
+        }
+    }
+    /// Overwrites everything except the body and the components with `state`
+    pub fn set_state(&mut self, state: Spacecraft) {
+        *self = Spacecraft {
+            components: std::mem::take(&mut self.components),
+            body: std::mem::take(&mut self.body),
+            fuel_lines: std::mem::take(&mut self.fuel_lines),
+            ..state
+        };
     }
 
     pub fn compute_mass(&self) -> f32 {
@@ -177,16 +496,7 @@ impl Spacecraft {
     }
 
     fn inertia(&self) -> f32 {
-        self.components
-            .values()
-            .map(|x| {
-                x.body()
-                    .centered_position()
-                    .distance(self.center_of_mass)
-                    .powi(2)
-                    * x.mass()
-            })
-            .sum()
+        components_inertia(&self.components, self.center_of_mass)
     }
     /// Applies a force in a local coordinate system
     fn apply_force_local(&mut self, origin: Vec2, direction: Vec2) {
@@ -196,19 +506,17 @@ impl Spacecraft {
             (origin - self.center_of_mass).perp_dot(direction) / self.inertia;
     }
     fn center_of_mass(&self) -> Vec2 {
-        let mut result = Vec2::ZERO;
-        let mut total_mass = 0.;
-        for component in self.components.values() {
-            result += component.body().centered_position() * component.mass();
-            total_mass += component.mass();
-        }
-        result / total_mass
+        components_center_of_mass(&self.components)
     }
     pub fn execute_component_cmd(&mut self, component_id: ComponentId, cmd: ComponentCmd) {
         if let Some(component) = self.components.get_mut(&component_id) {
             component.handle_cmd(cmd);
         }
     }
+    /// Converts a world position to the coordinates of the component grid
+    pub fn world_to_grid(&self, world_pos: Vec2) -> Vec2 {
+        self.body.world_to_relative(world_pos) + self.center_of_mass
+    }
     pub fn component_position_local(&self, component_body: &ComponentBody) -> Vec2 {
         (component_body.centered_position() - self.center_of_mass).rotate_rad(self.body.rotation)
     }
@@ -223,48 +531,88 @@ impl Spacecraft {
         self.mass
     }
 
+    /// Active shields covering the world `position` absorb the damage first, the rest enters the
+    /// hull there and penetrates towards the center of mass.
+    /// Components along the way take their share in order, armor stops everything behind it
+    /// until it is destroyed and the damage weakens with every cell it travels.
     pub fn apply_damage(&mut self, damage: f32, position: Vec2) -> Vec<(Material, f32)> {
-        let position = position - self.body.position;
+        let hit = self.world_to_grid(position);
+        let damage = self.absorb_by_shields(damage, hit);
+        if damage <= 0. {
+            return vec![];
+        }
+        let Some(nearest) = self
+            .components
+            .values()
+            .map(|x| x.body().centered_position())
+            .min_by(|a, b| a.distance(hit).partial_cmp(&b.distance(hit)).unwrap())
+        else {
+            return vec![];
+        };
+        // the damage starts at the first component in case the hull was hit in an empty corner
+        let entry = if hit.distance(nearest) > 0.5 {
+            nearest
+        } else {
+            hit
+        };
+        let direction = (self.center_of_mass - hit).normalize_or_zero();
+
+        let mut path = self
+            .components
+            .iter_mut()
+            .filter_map(|(_, component)| {
+                let offset = component.body().centered_position() - entry;
+                let along = offset.dot(direction);
+                let lateral = offset.perp_dot(direction).abs();
+                (along >= -DAMAGE_SPREAD && lateral <= DAMAGE_SPREAD || offset.length() < 0.01)
+                    .then_some((along.max(0.), component))
+            })
+            .collect::<Vec<_>>();
+        path.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
         let mut result = vec![];
         let mut damage = damage;
-        let mut components = self.components.iter_mut().collect::<Vec<_>>();
-        components.sort_by(|a, b| {
-            let a_position = (a.1.body().centered_position() - self.center_of_mass)
-                .rotate_rad(self.body.rotation);
-            let b_position = (b.1.body().centered_position() - self.center_of_mass)
-                .rotate_rad(self.body.rotation);
-            a_position
-                .distance(position)
-                .partial_cmp(&b_position.distance(position))
-                .unwrap()
-        });
-        // println!("{:?} distance: {:?}", components[0], (components[0].1.body().centered_position()-self.center_of_mass).rotate_rad(self.transform.rotation));
-        for (_, component) in components {
-            let component_damage = damage.min(component.health());
+        for (along, component) in path {
+            let falloff = (1. - along * DAMAGE_FALLOFF_PER_CELL).max(0.);
+            let damage_absorption = component.body().damage_absorption;
+            let component_damage = (damage * falloff * damage_absorption)
+                .min(component.health())
+                .max(0.);
             component.apply_damage(component_damage);
             damage -= component_damage;
             self.health -= component_damage;
+            let body = component.body();
             result.extend(
-                component
-                    .body()
-                    .origin
-                    .materials()
-                    .into_iter()
+                body.materials
+                    .iter()
                     .map(|(material, amount)| {
-                        (
-                            material,
-                            amount * component_damage / component.body().origin.health(),
-                        )
+                        (*material, amount * component_damage / body.max_health)
                     })
                     .collect::<Vec<(Material, f32)>>(),
             );
-            if damage <= 0. {
+            if damage <= 0. || (damage_absorption >= 1. && component.health() > 0.) {
                 break;
             }
         }
         result
     }
 
+    /// Shields covering the grid position `hit` absorb as much of the damage as they can,
+    /// returns the damage that passes through
+    fn absorb_by_shields(&mut self, mut damage: f32, hit: Vec2) -> f32 {
+        for component in self.components.values_mut() {
+            if damage <= 0. {
+                break;
+            }
+            if let Component::ShieldGenerator(shield) = component {
+                if shield.covers(hit) {
+                    damage -= shield.absorb(damage);
+                }
+            }
+        }
+        damage
+    }
+
     pub fn destroyed(&self) -> bool {
         self.components.is_empty()
     }
@@ -276,3 +624,248 @@ impl Spacecraft {
         self.body.position.distance(position) < 5.
     }
 }
+
+/// Test fixture building spacecrafts from the default catalogue, starts with the central
+/// component alone, owned by player 0 and resting at the origin
+#[cfg(test)]
+pub(crate) struct SpacecraftBuilder {
+    pub structure: SpacecraftStructure,
+    pub owner: PlayerId,
+    pub body: GameObjectBody,
+}
+
+#[cfg(test)]
+impl SpacecraftBuilder {
+    pub fn new() -> Self {
+        Self {
+            structure: SpacecraftStructure::new(),
+            owner: 0,
+            body: GameObjectBody::new(Vec2::ZERO, Vec2::ZERO, 0., 0., vec![]),
+        }
+    }
+    pub fn with(
+        mut self,
+        component_type: ComponentType,
+        position: IVec2,
+        orientation: Orientation,
+    ) -> Self {
+        self.structure
+            .component_placeholders
+            .push(ComponentPlaceholder::new(
+                component_type,
+                position,
+                orientation,
+            ));
+        self
+    }
+    pub fn at(mut self, position: Vec2, velocity: Vec2, rotation: f32) -> Self {
+        self.body = GameObjectBody::new(position, velocity, rotation, 0., vec![]);
+        self
+    }
+    pub fn owner(mut self, owner: PlayerId) -> Self {
+        self.owner = owner;
+        self
+    }
+    pub fn build(self) -> Spacecraft {
+        Spacecraft::build(
+            self.structure,
+            self.owner,
+            self.body,
+            &ComponentCatalogue::default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Central block with a two block wing on each side, ids 1, 2 are the left wing
+    /// and 3, 4 the right wing
+    fn winged_spacecraft(rotation: f32) -> Spacecraft {
+        [-1, -2, 1, 2]
+            .into_iter()
+            .fold(SpacecraftBuilder::new(), |builder, x| {
+                builder.with(ComponentType::STEEL_BLOCK, ivec2(x, 0), Orientation::Up)
+            })
+            .at(vec2(100., -40.), Vec2::ZERO, rotation)
+            .build()
+    }
+
+    fn damaged(spacecraft: &Spacecraft) -> Vec<ComponentId> {
+        spacecraft
+            .components
+            .iter()
+            .filter(|(_, c)| c.health() < c.body().max_health)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    #[test]
+    fn world_to_grid_roundtrip() {
+        let spacecraft = winged_spacecraft(1.3);
+        let grid_pos = vec2(-2., 0.3);
+        let world_pos = spacecraft
+            .body
+            .relative_to_world(grid_pos - spacecraft.center_of_mass);
+        assert!(spacecraft.world_to_grid(world_pos).distance(grid_pos) < 1e-4);
+    }
+
+    #[test]
+    fn left_wing_hit_damages_left_wing() {
+        for rotation in [0., PI / 2., 2.5, -PI] {
+            let mut spacecraft = winged_spacecraft(rotation);
+            let left_tip = spacecraft
+                .body
+                .relative_to_world(vec2(-2.5, 0.) - spacecraft.center_of_mass);
+
+            spacecraft.apply_damage(10., left_tip);
+            // the outer block absorbs everything, the rest of the wing stays intact
+            assert_eq!(damaged(&spacecraft), vec![2]);
+
+            let right_tip = spacecraft
+                .body
+                .relative_to_world(vec2(2.5, 0.) - spacecraft.center_of_mass);
+            spacecraft.apply_damage(10., right_tip);
+            assert_eq!(damaged(&spacecraft), vec![2, 4]);
+        }
+    }
+
+    #[test]
+    fn damage_penetrates_destroyed_armor() {
+        let mut spacecraft = winged_spacecraft(0.7);
+        let left_tip = spacecraft
+            .body
+            .relative_to_world(vec2(-2.5, 0.) - spacecraft.center_of_mass);
+
+        spacecraft.apply_damage(45., left_tip);
+        assert!(spacecraft.components[&2].health() <= 0.);
+        assert!(spacecraft.components[&1].health() < spacecraft.components[&1].body().max_health);
+        // the falloff keeps the center intact
+        assert_eq!(
+            spacecraft.components[&0].health(),
+            spacecraft.components[&0].body().max_health
+        );
+        assert_eq!(damaged(&spacecraft), vec![1, 2]);
+    }
+
+    #[test]
+    fn engines_draw_from_connected_tanks() {
+        let mut spacecraft = SpacecraftBuilder::new()
+            .with(ComponentType::RAPTOR_ENGINE, ivec2(1, 0), Orientation::Up)
+            .with(ComponentType::FUEL_TANK, ivec2(-1, 0), Orientation::Up)
+            .build();
+        assert_eq!(spacecraft.fuel_lines[&1], vec![2]);
+
+        let fuel = spacecraft.fuel();
+        spacecraft.execute_component_cmd(1, ComponentCmd::SetActive(true));
+        spacecraft.update(0.5);
+
+        let Component::Engine(engine) = &spacecraft.components[&1] else {
+            unreachable!()
+        };
+        assert_eq!(engine.fuel, engine.fuel_capacity);
+        assert!(spacecraft.fuel() < fuel);
+
+        let burned = fuel - spacecraft.fuel();
+        assert_eq!(spacecraft.refuel(burned * 2.), burned);
+        assert_eq!(spacecraft.fuel(), fuel);
+    }
+
+    #[test]
+    fn brownout_powers_higher_priority_first() {
+        let mut spacecraft = SpacecraftBuilder::new()
+            .with(ComponentType::RAPTOR_ENGINE, ivec2(1, 0), Orientation::Up)
+            .with(ComponentType::RAPTOR_ENGINE, ivec2(-1, 0), Orientation::Up)
+            .build();
+        for id in [1, 2] {
+            spacecraft.execute_component_cmd(id, ComponentCmd::SetActive(true));
+        }
+        spacecraft.execute_component_cmd(1, ComponentCmd::SetPriority(5));
+
+        // the central reactor can't power both engines at once
+        spacecraft.distribute_energy(1.);
+        let energy = |id| match &spacecraft.components[&id] {
+            Component::Engine(engine) => (engine.energy, engine.energy_demand(1.)),
+            _ => unreachable!(),
+        };
+        let (supplied, demand) = energy(2);
+        assert_eq!(supplied, demand);
+        let (supplied, demand) = energy(1);
+        assert!(supplied > 0. && supplied < demand);
+    }
+
+    #[test]
+    fn shield_absorbs_damage_on_its_side() {
+        let mut spacecraft = SpacecraftBuilder::new()
+            .with(ComponentType::STEEL_BLOCK, ivec2(-1, 0), Orientation::Up)
+            .with(
+                ComponentType::SHIELD_GENERATOR,
+                ivec2(1, 0),
+                Orientation::Right,
+            )
+            .at(Vec2::ZERO, Vec2::ZERO, 0.4)
+            .build();
+        let shield_strength = |spacecraft: &Spacecraft| match &spacecraft.components[&2] {
+            Component::ShieldGenerator(shield) => shield.strength,
+            _ => unreachable!(),
+        };
+        let full_strength = shield_strength(&spacecraft);
+
+        let front = spacecraft
+            .body
+            .relative_to_world(vec2(1.5, 0.) - spacecraft.center_of_mass);
+        spacecraft.apply_damage(10., front);
+        assert!(damaged(&spacecraft).is_empty());
+        assert_eq!(shield_strength(&spacecraft), full_strength - 10.);
+
+        let back = spacecraft
+            .body
+            .relative_to_world(vec2(-1.5, 0.) - spacecraft.center_of_mass);
+        spacecraft.apply_damage(10., back);
+        assert_eq!(damaged(&spacecraft), vec![1]);
+        assert_eq!(shield_strength(&spacecraft), full_strength - 10.);
+
+        spacecraft.execute_component_cmd(2, ComponentCmd::SetActive(false));
+        spacecraft.apply_damage(10., front);
+        assert!(damaged(&spacecraft).contains(&2));
+    }
+
+    #[test]
+    fn laser_hits_the_nearest_object_in_its_way() {
+        let mut spacecraft = SpacecraftBuilder::new()
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, 1), Orientation::Up)
+            .with(ComponentType::LASER_WEAPON, ivec2(0, 1), Orientation::Up)
+            .build();
+        // top components are numbered from 256
+        spacecraft.execute_component_cmd(258, ComponentCmd::SetActive(true));
+
+        let mut game = Game::with_seed(0);
+        game.handle_game_object_effect(GameObjectEffect::SpawnSpacecraft(spacecraft));
+        for y in [10., 20.] {
+            let asteroid = Asteroid::new(
+                vec2(0., y),
+                Vec2::ZERO,
+                0.,
+                2.,
+                Material::Iron,
+                &mut game.rng,
+            );
+            game.handle_game_object_effect(GameObjectEffect::SpawnAsteroid(asteroid));
+        }
+        game.update(0.1);
+
+        let beam_end = game
+            .events
+            .iter()
+            .find_map(|event| match event {
+                GameEvent::BeamFired(_, end) => Some(*end),
+                _ => None,
+            })
+            .unwrap();
+        assert!(beam_end.x.abs() < 1e-3 && (7.5..8.5).contains(&beam_end.y));
+        let mut asteroids = game.asteroids();
+        asteroids.sort_by(|a, b| a.body.position.y.total_cmp(&b.body.position.y));
+        assert!(asteroids[0].health() < asteroids[1].health());
+    }
+}
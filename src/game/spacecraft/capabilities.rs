@@ -0,0 +1,135 @@
+use super::flight_controller::{allocate_thrust, engine_mounts};
+use super::*;
+
+/// Number of directions in which the linear acceleration is measured
+pub const CAPABILITY_DIRECTIONS: usize = 8;
+/// Weight of the angular acceleration when measuring the linear one, keeps the spacecraft from
+/// turning while it accelerates
+const TURN_FREE_WEIGHT: f32 = 10.;
+
+/// Flight characteristics of a spacecraft structure, in grid coordinates
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Capabilities {
+    pub mass: f32,
+    pub center_of_mass: Vec2,
+    pub inertia: f32,
+    /// Largest acceleration along each of the `CAPABILITY_DIRECTIONS` directions without turning,
+    /// the first one is along the x axis and they follow counter-clockwise
+    pub linear_acceleration: Vec<(Vec2, f32)>,
+    /// Largest counter-clockwise angular acceleration
+    pub angular_acceleration_left: f32,
+    /// Largest clockwise angular acceleration
+    pub angular_acceleration_right: f32,
+    /// Force and torque of every engine at full power without gimbal, the columns of the matrix
+    /// mapping engine powers to the net force and torque
+    pub thrust_coupling: BTreeMap<ComponentId, (Vec2, f32)>,
+}
+
+impl Capabilities {
+    /// Largest linear acceleration along `direction`, interpolated between the measured ones
+    pub fn linear_acceleration(&self, direction: Vec2) -> f32 {
+        let step = 2. * PI / CAPABILITY_DIRECTIONS as f32;
+        let angle = direction.angle().rem_euclid(2. * PI) / step;
+        let i = angle.floor() as usize % CAPABILITY_DIRECTIONS;
+        let t = angle.fract();
+        self.linear_acceleration[i].1 * (1. - t)
+            + self.linear_acceleration[(i + 1) % CAPABILITY_DIRECTIONS].1 * t
+    }
+}
+
+impl SpacecraftStructure {
+    /// Computes the flight characteristics of the spacecraft the structure would build,
+    /// None if it can't be built
+    pub fn capabilities(&self, catalogue: &ComponentCatalogue) -> Option<Capabilities> {
+        if !self.valid(catalogue) {
+            return None;
+        }
+        let spacecraft = Spacecraft::build(
+            self.clone(),
+            0,
+            GameObjectBody::new(Vec2::ZERO, Vec2::ZERO, 0., 0., vec![]),
+            catalogue,
+        );
+        let (mass, inertia) = (spacecraft.mass, spacecraft.inertia);
+        let engines = engine_mounts(&spacecraft.components, spacecraft.center_of_mass);
+
+        let total_thrust = engines.iter().map(|engine| engine.thrust).sum::<f32>();
+        // out of reach, so the allocation pushes as hard as it can
+        let unreachable = 2. * total_thrust / mass.max(f32::EPSILON);
+
+        let linear_acceleration = (0..CAPABILITY_DIRECTIONS)
+            .map(|i| {
+                let direction = Vec2::from_angle(2. * PI * i as f32 / CAPABILITY_DIRECTIONS as f32);
+                let allocation = allocate_thrust(
+                    &engines,
+                    mass,
+                    inertia,
+                    (direction * unreachable, 0.),
+                    TURN_FREE_WEIGHT,
+                );
+                (direction, allocation.linear.dot(direction).max(0.))
+            })
+            .collect();
+
+        let max_torque = |sign: f32| {
+            engines
+                .iter()
+                .map(|engine| {
+                    [-MAX_GIMBAL, 0., MAX_GIMBAL]
+                        .into_iter()
+                        .map(|rotation| {
+                            let force = Vec2::from_angle(engine.orientation + rotation);
+                            engine.arm.perp_dot(force) * engine.thrust * sign
+                        })
+                        .fold(0., f32::max)
+                })
+                .sum::<f32>()
+                / inertia.max(f32::EPSILON)
+        };
+
+        let thrust_coupling = engines
+            .iter()
+            .map(|engine| {
+                let force = Vec2::from_angle(engine.orientation) * engine.thrust;
+                (engine.id, (force, engine.arm.perp_dot(force)))
+            })
+            .collect();
+
+        Some(Capabilities {
+            mass,
+            center_of_mass: spacecraft.center_of_mass,
+            inertia,
+            linear_acceleration,
+            angular_acceleration_left: max_torque(1.),
+            angular_acceleration_right: max_torque(-1.),
+            thrust_coupling,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    #[test]
+    fn single_engine_design_can_only_push_forward() {
+        let capabilities = SpacecraftBuilder::new()
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(-2, 0),
+                Orientation::Right,
+            )
+            .structure
+            .capabilities(&ComponentCatalogue::default())
+            .unwrap();
+
+        let forward = capabilities.linear_acceleration(Vec2::X);
+        assert!(forward > 0.);
+        assert_eq!(capabilities.linear_acceleration(-Vec2::X), 0.);
+        assert_eq!(capabilities.thrust_coupling.len(), 1);
+        assert!(capabilities.angular_acceleration_left > 0.);
+        assert!(capabilities.angular_acceleration_right > 0.);
+        assert!(capabilities.center_of_mass.x < 0.);
+    }
+}
@@ -1,14 +1,25 @@
+mod battery;
 mod block;
+mod catalogue;
 mod engine;
+mod fuel_tank;
+mod laser_weapon;
 mod orientation;
+mod reactor;
+mod shield_generator;
 mod weapon;
 
 use crate::prelude::*;
+pub use catalogue::*;
+pub use engine::MAX_GIMBAL;
 use glam::IVec2;
 pub use orientation::*;
 use std::fmt::Debug;
 
-use self::{block::Block, engine::Engine, weapon::Weapon};
+use self::{
+    battery::Battery, block::Block, engine::Engine, fuel_tank::FuelTank, laser_weapon::LaserWeapon,
+    reactor::Reactor, shield_generator::ShieldGenerator, weapon::Weapon,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 // #[serde(tag = "cmd", content = "args")]
@@ -16,6 +27,12 @@ pub enum ComponentCmd {
     SetActive(bool),
     SetPower(f32),
     SetRotation(f32),
+    /// Consumers with a lower priority value are powered first during a brownout
+    SetPriority(u8),
+    /// Target of the projectiles launched by a weapon and of the designated targeting mode,
+    /// None clears it
+    SetTarget(Option<Target>),
+    SetTargetingMode(TargetingMode),
     SelfDestruct,
 }
 
@@ -27,6 +44,11 @@ pub enum Component {
     Block(Block),
     Engine(Engine),
     Weapon(Weapon),
+    FuelTank(FuelTank),
+    Reactor(Reactor),
+    Battery(Battery),
+    ShieldGenerator(ShieldGenerator),
+    LaserWeapon(LaserWeapon),
 }
 
 impl Component {
@@ -35,6 +57,11 @@ impl Component {
             Component::Block(block) => &block.body,
             Component::Engine(engine) => &engine.body,
             Component::Weapon(weapon) => &weapon.body,
+            Component::FuelTank(fuel_tank) => &fuel_tank.body,
+            Component::Reactor(reactor) => &reactor.body,
+            Component::Battery(battery) => &battery.body,
+            Component::ShieldGenerator(shield) => &shield.body,
+            Component::LaserWeapon(laser) => &laser.body,
         }
     }
     pub fn body_mut(&mut self) -> &mut ComponentBody {
@@ -42,6 +69,11 @@ impl Component {
             Component::Block(block) => &mut block.body,
             Component::Engine(engine) => &mut engine.body,
             Component::Weapon(weapon) => &mut weapon.body,
+            Component::FuelTank(fuel_tank) => &mut fuel_tank.body,
+            Component::Reactor(reactor) => &mut reactor.body,
+            Component::Battery(battery) => &mut battery.body,
+            Component::ShieldGenerator(shield) => &mut shield.body,
+            Component::LaserWeapon(laser) => &mut laser.body,
         }
     }
     pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
@@ -49,6 +81,11 @@ impl Component {
             Component::Block(block) => block.update(time),
             Component::Engine(engine) => engine.update(time),
             Component::Weapon(weapon) => weapon.update(time),
+            Component::FuelTank(fuel_tank) => fuel_tank.update(time),
+            Component::Reactor(reactor) => reactor.update(time),
+            Component::Battery(battery) => battery.update(time),
+            Component::ShieldGenerator(shield) => shield.update(time),
+            Component::LaserWeapon(laser) => laser.update(time),
         };
         self.body_mut().update(time);
         result
@@ -58,6 +95,11 @@ impl Component {
             Component::Block(block) => block.mass(),
             Component::Engine(engine) => engine.mass(),
             Component::Weapon(weapon) => weapon.mass,
+            Component::FuelTank(fuel_tank) => fuel_tank.mass(),
+            Component::Reactor(reactor) => reactor.mass(),
+            Component::Battery(battery) => battery.mass(),
+            Component::ShieldGenerator(shield) => shield.mass(),
+            Component::LaserWeapon(laser) => laser.mass(),
         }
     }
     pub fn health(&self) -> f32 {
@@ -65,6 +107,11 @@ impl Component {
             Component::Block(block) => block.health(),
             Component::Engine(engine) => engine.health(),
             Component::Weapon(weapon) => weapon.health,
+            Component::FuelTank(fuel_tank) => fuel_tank.health(),
+            Component::Reactor(reactor) => reactor.health(),
+            Component::Battery(battery) => battery.health(),
+            Component::ShieldGenerator(shield) => shield.health(),
+            Component::LaserWeapon(laser) => laser.health(),
         }
     }
     pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
@@ -72,6 +119,11 @@ impl Component {
             Component::Block(block) => block.handle_cmd(cmd),
             Component::Engine(engine) => engine.handle_cmd(cmd),
             Component::Weapon(weapon) => weapon.handle_cmd(cmd),
+            Component::FuelTank(fuel_tank) => fuel_tank.handle_cmd(cmd),
+            Component::Reactor(reactor) => reactor.handle_cmd(cmd),
+            Component::Battery(battery) => battery.handle_cmd(cmd),
+            Component::ShieldGenerator(shield) => shield.handle_cmd(cmd),
+            Component::LaserWeapon(laser) => laser.handle_cmd(cmd),
         }
     }
     pub fn apply_damage(&mut self, damage: f32) {
@@ -79,18 +131,52 @@ impl Component {
             Component::Block(block) => block.apply_damage(damage),
             Component::Engine(engine) => engine.apply_damage(damage),
             Component::Weapon(weapon) => weapon.apply_damage(damage),
+            Component::FuelTank(fuel_tank) => fuel_tank.apply_damage(damage),
+            Component::Reactor(reactor) => reactor.apply_damage(damage),
+            Component::Battery(battery) => battery.apply_damage(damage),
+            Component::ShieldGenerator(shield) => shield.apply_damage(damage),
+            Component::LaserWeapon(laser) => laser.apply_damage(damage),
+        }
+    }
+    /// Priority and amount of energy the component needs for its update to `time`
+    pub fn energy_demand(&self, time: f32) -> Option<(u8, f32)> {
+        match self {
+            Component::Engine(engine) => Some((engine.priority, engine.energy_demand(time))),
+            Component::Weapon(weapon) => Some((weapon.priority, weapon.energy_demand(time))),
+            Component::ShieldGenerator(shield) => {
+                Some((shield.priority, shield.energy_demand(time)))
+            }
+            Component::LaserWeapon(laser) => Some((laser.priority, laser.energy_demand(time))),
+            _ => None,
+        }
+    }
+    /// Hands the energy allocated by the power grid to a consumer
+    pub fn supply_energy(&mut self, energy: f32) {
+        match self {
+            Component::Engine(engine) => engine.energy += energy,
+            Component::Weapon(weapon) => weapon.charge += energy,
+            Component::ShieldGenerator(shield) => shield.energy += energy,
+            Component::LaserWeapon(laser) => laser.energy += energy,
+            _ => {}
         }
     }
 }
 
 /// The body represents static properties of a component
 /// Position specifies a point in grid along which is component rotated when orientation is set
+/// The properties of the definition the component was built from are copied into the body,
+/// so built components don't depend on later changes of the catalogue
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ComponentBody {
     pub position: IVec2,
     pub orientation: Orientation,
     pub origin: ComponentType,
     pub cur_time: f32,
+    pub scale: UVec2,
+    pub top: Option<TopComponentProperties>,
+    pub max_health: f32,
+    pub damage_absorption: f32,
+    pub materials: BTreeMap<Material, f32>,
 }
 
 impl ComponentBody {
@@ -100,10 +186,10 @@ impl ComponentBody {
         self.cur_time = time;
     }
     pub fn scale(&self) -> UVec2 {
-        self.origin.scale()
+        self.scale
     }
-    pub fn top(&self) -> Option<TopComponentProperties> {
-        self.origin.top()
+    pub fn top(&self) -> Option<&TopComponentProperties> {
+        self.top.as_ref()
     }
     pub fn centered_position(&self) -> Vec2 {
         self.position.as_vec2()
@@ -133,8 +219,8 @@ impl ComponentBody {
         vec![
             vec2(-0.5, -0.5),
             vec2(-0.5, 0.5),
-            vec2(self.origin.scale().x as f32 - 0.5, -0.5),
-            self.origin.scale().as_vec2() - vec2(0.5, 0.5),
+            vec2(self.scale.x as f32 - 0.5, -0.5),
+            self.scale.as_vec2() - vec2(0.5, 0.5),
         ]
         .into_iter()
         .map(|v| v.orient(self.orientation) + self.position.as_vec2())
@@ -143,136 +229,37 @@ impl ComponentBody {
 }
 
 pub enum ComponentEffect {
-    CreateProjectile(ProjectileType, Vec2, Vec2, f32), // position, velocity, rotation
+    CreateProjectile(ProjectileType, Vec2, Vec2, f32, Option<Target>), // position, velocity, rotation, target
     ApplyForce(Vec2), // for now only direction is sufficient, for more detail we might consider to add origin too
+    FireBeam(Vec2, f32, f32, f32), // position, rotation, range, damage
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, strum::Display)]
-pub enum ComponentType {
-    Central,
-    SteelBlock,
-    RaptorEngine,
-    KineticWeapon,
-    MissileLauncher,
-}
+/// Id of a component definition in the `ComponentCatalogue`
+#[derive(
+    Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub struct ComponentType(pub u16);
 
 impl ComponentType {
-    pub fn build(&self, position: IVec2, orientation: Orientation) -> Component {
-        let body = ComponentBody {
-            position,
-            orientation,
-            origin: *self,
-            cur_time: 0.,
-        };
-        let health = self.health();
-        match self {
-            ComponentType::Central => Component::Block(Block::new(body, 10., health)),
-            ComponentType::SteelBlock => Component::Block(Block::new(body, 50., health)),
-            ComponentType::RaptorEngine => Component::Engine(Engine::new(
-                body,
-                10.,
-                health,
-                5000.,
-                500000.,
-                0.001,
-                vec2(-0.8, 0.),
-            )),
-            ComponentType::KineticWeapon => Component::Weapon(Weapon::new(
-                body,
-                10.,
-                health,
-                5.,
-                ProjectileType::Bullet,
-                100.,
-                vec2(0.48, 0.),
-                PI,
-            )),
-            ComponentType::MissileLauncher => Component::Weapon(Weapon::new(
-                body,
-                40.,
-                health,
-                20.,
-                ProjectileType::Missile,
-                30.,
-                vec2(1.48, 0.),
-                PI / 2.,
-            )),
-        }
-    }
-    pub fn health(&self) -> f32 {
-        match &self {
-            ComponentType::Central => 60.,
-            ComponentType::SteelBlock => 30.,
-            ComponentType::RaptorEngine => 55.,
-            ComponentType::KineticWeapon => 15.,
-            ComponentType::MissileLauncher => 30.,
-        }
-    }
-    pub fn scale(&self) -> UVec2 {
-        match self {
-            ComponentType::Central => uvec2(1, 1),
-            ComponentType::SteelBlock => uvec2(1, 1),
-            ComponentType::RaptorEngine => uvec2(2, 1),
-            ComponentType::KineticWeapon => uvec2(1, 1),
-            ComponentType::MissileLauncher => uvec2(2, 1),
-        }
-    }
-    pub fn build_time(&self) -> f32 {
-        match self {
-            ComponentType::Central => 0.,
-            ComponentType::SteelBlock => 1.,
-            ComponentType::RaptorEngine => 2.,
-            ComponentType::KineticWeapon => 2.,
-            ComponentType::MissileLauncher => 3.,
-        }
-    }
-    pub fn top(&self) -> Option<TopComponentProperties> {
-        match self {
-            ComponentType::Central => None,
-            ComponentType::SteelBlock => None,
-            ComponentType::RaptorEngine => None,
-            ComponentType::KineticWeapon => Some(TopComponentProperties {
-                occupies: vec![ivec2(0, 0)],
-            }),
-            ComponentType::MissileLauncher => Some(TopComponentProperties {
-                occupies: vec![
-                    ivec2(0, 0),
-                    ivec2(1, 0),
-                    ivec2(0, 1),
-                    ivec2(1, 1),
-                    ivec2(1, -1),
-                    ivec2(0, -1),
-                ],
-            }),
-        }
-    }
-    pub fn materials(&self) -> Vec<(Material, f32)> {
-        match self {
-            ComponentType::Central => vec![(Material::Nickel, 15.), (Material::Iron, 3.), (Material::Silicates, 10.)],
-            ComponentType::SteelBlock => vec![(Material::Iron, 5.), (Material::Nickel, 5.), (Material::Copper, 2.)],
-            ComponentType::RaptorEngine => vec![
-                (Material::Iron, 10.),
-                (Material::Nickel, 30.),
-                (Material::Copper, 20.),
-                (Material::Silicates, 10.)
-            ],
-            ComponentType::KineticWeapon => vec![
-                (Material::Silicates, 5.),
-                (Material::Iron, 5.),
-                (Material::Copper, 7.),
-                (Material::Carbon, 2.),
-            ],
-            ComponentType::MissileLauncher => vec![
-                (Material::Iron, 5.),
-                (Material::Silicates, 5.),
-                (Material::Nickel, 5.),
-                (Material::Copper, 3.),
-                (Material::Carbon, 4.),
-            ],
-        }
+    pub const CENTRAL: ComponentType = ComponentType(0);
+    pub const STEEL_BLOCK: ComponentType = ComponentType(1);
+    pub const RAPTOR_ENGINE: ComponentType = ComponentType(2);
+    pub const KINETIC_WEAPON: ComponentType = ComponentType(3);
+    pub const MISSILE_LAUNCHER: ComponentType = ComponentType(4);
+    pub const FUEL_TANK: ComponentType = ComponentType(5);
+    pub const REACTOR: ComponentType = ComponentType(6);
+    pub const BATTERY: ComponentType = ComponentType(7);
+    pub const SHIELD_GENERATOR: ComponentType = ComponentType(8);
+    pub const LASER_WEAPON: ComponentType = ComponentType(9);
+}
+
+impl std::fmt::Display for ComponentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct TopComponentProperties {
     pub occupies: Vec<IVec2>,
 }
@@ -0,0 +1,57 @@
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Battery {
+    pub body: ComponentBody,
+    mass: f32,
+    health: f32,
+    pub energy: f32,
+    pub capacity: f32,
+}
+
+impl Battery {
+    pub fn new(body: ComponentBody, mass: f32, health: f32, capacity: f32) -> Self {
+        Self {
+            body,
+            mass,
+            health,
+            energy: capacity,
+            capacity,
+        }
+    }
+}
+
+impl Battery {
+    pub fn body(&self) -> &ComponentBody {
+        &self.body
+    }
+    pub fn update(&mut self, _time: f32) -> Vec<ComponentEffect> {
+        vec![]
+    }
+    /// Stores up to `amount` of energy and returns how much fit in
+    pub fn charge(&mut self, amount: f32) -> f32 {
+        let charged = (self.capacity - self.energy).min(amount).max(0.);
+        self.energy += charged;
+        charged
+    }
+    /// Takes up to `amount` of energy and returns how much was taken
+    pub fn discharge(&mut self, amount: f32) -> f32 {
+        let discharged = self.energy.min(amount).max(0.);
+        self.energy -= discharged;
+        discharged
+    }
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
+        if let ComponentCmd::SelfDestruct = cmd {
+            self.health = 0.;
+        }
+    }
+    pub fn apply_damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+}
@@ -0,0 +1,775 @@
+use super::*;
+
+/// Format version of `ComponentCatalogue`, catalogues of other versions are rejected
+pub const COMPONENT_CATALOGUE_VERSION: u32 = 1;
+/// Fewest seconds between two shots of a weapon
+pub const MIN_FIRE_RATE: f32 = 0.01;
+
+/// Definitions of all components which can be built, shipped inside `Game` so servers can
+/// change the balance or add components without recompiling the core.
+/// `Default` gives the built-in components.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ComponentCatalogue {
+    pub version: u32,
+    pub components: BTreeMap<ComponentType, ComponentDefinition>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ComponentDefinition {
+    pub name: String,
+    pub mass: f32,
+    pub health: f32,
+    /// Fraction of passing damage the component takes itself, the rest penetrates further
+    pub damage_absorption: f32,
+    pub scale: UVec2,
+    #[serde(default)]
+    pub top: Option<TopComponentProperties>,
+    pub build_time: f32,
+    pub materials: BTreeMap<Material, f32>,
+    /// Energy consumers with a lower value are powered first during a brownout
+    #[serde(default)]
+    pub power_priority: u8,
+    pub kind: ComponentKind,
+}
+
+/// Behaviour of the component together with the properties specific to it
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum ComponentKind {
+    Block,
+    Engine {
+        thrust: f32,
+        fuel: f32,
+        fuel_density: f32,
+        ignition_point: Vec2,
+        /// Energy per second at full power
+        #[serde(default)]
+        energy_consumption: f32,
+    },
+    Weapon {
+        /// Seconds between two shots
+        fire_rate: f32,
+        projectile_type: ProjectileType,
+        projectile_speed: f32,
+        launch_point: Vec2,
+        max_angle: f32,
+        #[serde(default)]
+        shot_energy: f32,
+        /// Reach of the auto-targeting
+        #[serde(default)]
+        range: f32,
+        /// Angular speed of the turret, zero for weapons which can't turn
+        #[serde(default)]
+        turn_rate: f32,
+        /// Largest deviation of a shot from the rotation
+        #[serde(default)]
+        spread: f32,
+        /// Zero for weapons which don't need reloading
+        #[serde(default)]
+        magazine_size: u32,
+        #[serde(default)]
+        reload_time: f32,
+    },
+    FuelTank {
+        capacity: f32,
+        fuel_density: f32,
+        /// Fraction of the capacity leaked per second at zero health
+        leak_rate: f32,
+    },
+    Reactor {
+        /// Energy per second at full power
+        output: f32,
+    },
+    Battery {
+        capacity: f32,
+    },
+    ShieldGenerator {
+        max_strength: f32,
+        /// Strength regained per second
+        recharge_rate: f32,
+        /// Covered angle to both sides of the facing
+        arc: f32,
+        radius: f32,
+        /// Energy per point of strength recharged
+        #[serde(default)]
+        recharge_energy: f32,
+    },
+    LaserWeapon {
+        range: f32,
+        /// Damage per second dealt at the hit point
+        damage_rate: f32,
+        launch_point: Vec2,
+        max_angle: f32,
+        /// Energy per second while firing
+        #[serde(default)]
+        energy_consumption: f32,
+    },
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    InvalidFormat(String),
+    UnsupportedVersion(u32),
+    /// The central component has to exist and be a block or reactor without a top footprint
+    InvalidCentral,
+    InvalidValue(ComponentType, String), // component, description
+}
+
+impl ComponentDefinition {
+    pub fn build(
+        &self,
+        origin: ComponentType,
+        position: IVec2,
+        orientation: Orientation,
+    ) -> Component {
+        let body = ComponentBody {
+            position,
+            orientation,
+            origin,
+            cur_time: 0.,
+            scale: self.scale,
+            top: self.top.clone(),
+            max_health: self.health,
+            damage_absorption: self.damage_absorption,
+            materials: self.materials.clone(),
+        };
+        match self.kind {
+            ComponentKind::Block => Component::Block(Block::new(body, self.mass, self.health)),
+            ComponentKind::Engine {
+                thrust,
+                fuel,
+                fuel_density,
+                ignition_point,
+                energy_consumption,
+            } => Component::Engine(
+                Engine::new(
+                    body,
+                    self.mass,
+                    self.health,
+                    thrust,
+                    fuel,
+                    fuel_density,
+                    ignition_point,
+                )
+                .with_power_usage(energy_consumption, self.power_priority),
+            ),
+            ComponentKind::Weapon {
+                fire_rate,
+                projectile_type,
+                projectile_speed,
+                launch_point,
+                max_angle,
+                shot_energy,
+                range,
+                turn_rate,
+                spread,
+                magazine_size,
+                reload_time,
+            } => Component::Weapon(
+                Weapon::new(
+                    body,
+                    self.mass,
+                    self.health,
+                    fire_rate,
+                    projectile_type,
+                    projectile_speed,
+                    launch_point,
+                    max_angle,
+                )
+                .with_power_usage(shot_energy, self.power_priority)
+                .with_targeting(range, turn_rate)
+                .with_ammunition(magazine_size, reload_time, spread),
+            ),
+            ComponentKind::FuelTank {
+                capacity,
+                fuel_density,
+                leak_rate,
+            } => Component::FuelTank(FuelTank::new(
+                body,
+                self.mass,
+                self.health,
+                capacity,
+                fuel_density,
+                leak_rate,
+            )),
+            ComponentKind::Reactor { output } => {
+                Component::Reactor(Reactor::new(body, self.mass, self.health, output))
+            }
+            ComponentKind::Battery { capacity } => {
+                Component::Battery(Battery::new(body, self.mass, self.health, capacity))
+            }
+            ComponentKind::ShieldGenerator {
+                max_strength,
+                recharge_rate,
+                arc,
+                radius,
+                recharge_energy,
+            } => Component::ShieldGenerator(
+                ShieldGenerator::new(
+                    body,
+                    self.mass,
+                    self.health,
+                    max_strength,
+                    recharge_rate,
+                    arc,
+                    radius,
+                )
+                .with_power_usage(recharge_energy, self.power_priority),
+            ),
+            ComponentKind::LaserWeapon {
+                range,
+                damage_rate,
+                launch_point,
+                max_angle,
+                energy_consumption,
+            } => Component::LaserWeapon(
+                LaserWeapon::new(
+                    body,
+                    self.mass,
+                    self.health,
+                    range,
+                    damage_rate,
+                    launch_point,
+                    max_angle,
+                )
+                .with_power_usage(energy_consumption, self.power_priority),
+            ),
+        }
+    }
+    fn validate(&self, component_type: ComponentType) -> Result<(), CatalogueError> {
+        let invalid = |description: &str| {
+            Err(CatalogueError::InvalidValue(
+                component_type,
+                description.to_string(),
+            ))
+        };
+        let non_negative = |x: f32| x.is_finite() && x >= 0.;
+
+        // massless components leave the center of mass and collision impulses undefined
+        if !(self.mass.is_finite() && self.mass > 0.) {
+            return invalid("mass has to be positive");
+        }
+        if !(self.health.is_finite() && self.health > 0.) {
+            return invalid("health has to be positive");
+        }
+        if !(0. ..=1.).contains(&self.damage_absorption) {
+            return invalid("damage absorption has to be between 0 and 1");
+        }
+        if self.scale.x == 0 || self.scale.y == 0 {
+            return invalid("scale can't be zero");
+        }
+        if let Some(top) = &self.top {
+            if top.occupies.is_empty() {
+                return invalid("top component has to occupy at least one position");
+            }
+        }
+        if !non_negative(self.build_time) {
+            return invalid("build time has to be a non negative number");
+        }
+        if !self.materials.values().all(|x| non_negative(*x)) {
+            return invalid("material amounts have to be non negative numbers");
+        }
+        match self.kind {
+            ComponentKind::Block => {}
+            ComponentKind::Engine {
+                thrust,
+                fuel,
+                fuel_density,
+                ignition_point,
+                energy_consumption,
+            } => {
+                if !non_negative(thrust) || !non_negative(fuel) || !non_negative(fuel_density) {
+                    return invalid("thrust and fuel have to be non negative numbers");
+                }
+                if !non_negative(energy_consumption) {
+                    return invalid("energy consumption has to be a non negative number");
+                }
+                if !ignition_point.is_finite() {
+                    return invalid("ignition point has to be finite");
+                }
+            }
+            ComponentKind::Weapon {
+                fire_rate,
+                projectile_speed,
+                launch_point,
+                max_angle,
+                shot_energy,
+                range,
+                turn_rate,
+                spread,
+                reload_time,
+                ..
+            } => {
+                if !(fire_rate.is_finite() && fire_rate >= MIN_FIRE_RATE) {
+                    return invalid("fire rate has to be at least the minimum fire rate");
+                }
+                if !non_negative(projectile_speed) {
+                    return invalid("projectile speed has to be a non negative number");
+                }
+                if !(0. ..=PI).contains(&spread) || !non_negative(reload_time) {
+                    return invalid("spread and reload time have to be non negative");
+                }
+                if !non_negative(range) || !non_negative(turn_rate) {
+                    return invalid("range and turn rate have to be non negative");
+                }
+                if !non_negative(shot_energy) {
+                    return invalid("shot energy has to be a non negative number");
+                }
+                if !launch_point.is_finite() {
+                    return invalid("launch point has to be finite");
+                }
+                if !(0. ..=PI).contains(&max_angle) {
+                    return invalid("max angle has to be between 0 and PI");
+                }
+            }
+            ComponentKind::FuelTank {
+                capacity,
+                fuel_density,
+                leak_rate,
+            } => {
+                if !non_negative(capacity) || !non_negative(fuel_density) {
+                    return invalid("capacity and fuel density have to be non negative numbers");
+                }
+                if !non_negative(leak_rate) {
+                    return invalid("leak rate has to be a non negative number");
+                }
+            }
+            ComponentKind::Reactor { output } => {
+                if !non_negative(output) {
+                    return invalid("output has to be a non negative number");
+                }
+            }
+            ComponentKind::Battery { capacity } => {
+                if !non_negative(capacity) {
+                    return invalid("capacity has to be a non negative number");
+                }
+            }
+            ComponentKind::ShieldGenerator {
+                max_strength,
+                recharge_rate,
+                arc,
+                radius,
+                recharge_energy,
+            } => {
+                if !non_negative(max_strength)
+                    || !non_negative(recharge_rate)
+                    || !non_negative(recharge_energy)
+                {
+                    return invalid("shield strength and recharge have to be non negative");
+                }
+                if !(0. ..=PI).contains(&arc) {
+                    return invalid("arc has to be between 0 and PI");
+                }
+                if !non_negative(radius) {
+                    return invalid("radius has to be a non negative number");
+                }
+            }
+            ComponentKind::LaserWeapon {
+                range,
+                damage_rate,
+                launch_point,
+                max_angle,
+                energy_consumption,
+            } => {
+                if !non_negative(range) || !non_negative(damage_rate) {
+                    return invalid("range and damage rate have to be non negative");
+                }
+                if !non_negative(energy_consumption) {
+                    return invalid("energy consumption has to be a non negative number");
+                }
+                if !launch_point.is_finite() {
+                    return invalid("launch point has to be finite");
+                }
+                if !(0. ..=PI).contains(&max_angle) {
+                    return invalid("max angle has to be between 0 and PI");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ComponentCatalogue {
+    /// Parses and validates a catalogue in the format of `serialize_str`
+    pub fn load(data: &str) -> Result<Self, CatalogueError> {
+        let catalogue: Self =
+            deserialize_str(data).map_err(|e| CatalogueError::InvalidFormat(e.to_string()))?;
+        catalogue.validate()?;
+        Ok(catalogue)
+    }
+    pub fn validate(&self) -> Result<(), CatalogueError> {
+        if self.version != COMPONENT_CATALOGUE_VERSION {
+            return Err(CatalogueError::UnsupportedVersion(self.version));
+        }
+        match self.get(ComponentType::CENTRAL) {
+            Some(ComponentDefinition {
+                kind: ComponentKind::Block | ComponentKind::Reactor { .. },
+                top: None,
+                ..
+            }) => {}
+            _ => return Err(CatalogueError::InvalidCentral),
+        }
+        for (component_type, definition) in &self.components {
+            definition.validate(*component_type)?;
+        }
+        Ok(())
+    }
+    pub fn get(&self, component_type: ComponentType) -> Option<&ComponentDefinition> {
+        self.components.get(&component_type)
+    }
+    pub fn contains(&self, component_type: ComponentType) -> bool {
+        self.components.contains_key(&component_type)
+    }
+    /// Builds a component of the given type, None if the catalogue doesn't define it
+    pub fn build(
+        &self,
+        component_type: ComponentType,
+        position: IVec2,
+        orientation: Orientation,
+    ) -> Option<Component> {
+        self.get(component_type)
+            .map(|definition| definition.build(component_type, position, orientation))
+    }
+}
+
+impl Default for ComponentCatalogue {
+    fn default() -> Self {
+        let components = BTreeMap::from([
+            (
+                ComponentType::CENTRAL,
+                ComponentDefinition {
+                    name: "Central".to_string(),
+                    mass: 10.,
+                    health: 60.,
+                    damage_absorption: 0.8,
+                    scale: uvec2(1, 1),
+                    top: None,
+                    build_time: 0.,
+                    materials: BTreeMap::from([
+                        (Material::Nickel, 15.),
+                        (Material::Iron, 3.),
+                        (Material::Silicates, 10.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::Reactor { output: 25. },
+                },
+            ),
+            (
+                ComponentType::STEEL_BLOCK,
+                ComponentDefinition {
+                    name: "SteelBlock".to_string(),
+                    mass: 50.,
+                    health: 30.,
+                    damage_absorption: 1.,
+                    scale: uvec2(1, 1),
+                    top: None,
+                    build_time: 1.,
+                    materials: BTreeMap::from([
+                        (Material::Iron, 5.),
+                        (Material::Nickel, 5.),
+                        (Material::Copper, 2.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::Block,
+                },
+            ),
+            (
+                ComponentType::RAPTOR_ENGINE,
+                ComponentDefinition {
+                    name: "RaptorEngine".to_string(),
+                    mass: 10.,
+                    health: 55.,
+                    damage_absorption: 0.5,
+                    scale: uvec2(2, 1),
+                    top: None,
+                    build_time: 2.,
+                    materials: BTreeMap::from([
+                        (Material::Iron, 10.),
+                        (Material::Nickel, 30.),
+                        (Material::Copper, 20.),
+                        (Material::Silicates, 10.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::Engine {
+                        thrust: 5000.,
+                        fuel: 500000.,
+                        fuel_density: 0.001,
+                        ignition_point: vec2(-0.8, 0.),
+                        energy_consumption: 20.,
+                    },
+                },
+            ),
+            (
+                ComponentType::KINETIC_WEAPON,
+                ComponentDefinition {
+                    name: "KineticWeapon".to_string(),
+                    mass: 10.,
+                    health: 15.,
+                    damage_absorption: 0.3,
+                    scale: uvec2(1, 1),
+                    top: Some(TopComponentProperties {
+                        occupies: vec![ivec2(0, 0)],
+                    }),
+                    build_time: 2.,
+                    materials: BTreeMap::from([
+                        (Material::Silicates, 5.),
+                        (Material::Iron, 5.),
+                        (Material::Copper, 7.),
+                        (Material::Carbon, 2.),
+                    ]),
+                    power_priority: 1,
+                    kind: ComponentKind::Weapon {
+                        fire_rate: 5.,
+                        projectile_type: ProjectileType::Bullet,
+                        projectile_speed: 100.,
+                        launch_point: vec2(0.48, 0.),
+                        max_angle: PI,
+                        shot_energy: 5.,
+                        range: 150.,
+                        turn_rate: 3.,
+                        spread: 0.01,
+                        magazine_size: 0,
+                        reload_time: 0.,
+                    },
+                },
+            ),
+            (
+                ComponentType::MISSILE_LAUNCHER,
+                ComponentDefinition {
+                    name: "MissileLauncher".to_string(),
+                    mass: 40.,
+                    health: 30.,
+                    damage_absorption: 0.5,
+                    scale: uvec2(2, 1),
+                    top: Some(TopComponentProperties {
+                        occupies: vec![
+                            ivec2(0, 0),
+                            ivec2(1, 0),
+                            ivec2(0, 1),
+                            ivec2(1, 1),
+                            ivec2(1, -1),
+                            ivec2(0, -1),
+                        ],
+                    }),
+                    build_time: 3.,
+                    materials: BTreeMap::from([
+                        (Material::Iron, 5.),
+                        (Material::Silicates, 5.),
+                        (Material::Nickel, 5.),
+                        (Material::Copper, 3.),
+                        (Material::Carbon, 4.),
+                    ]),
+                    power_priority: 1,
+                    kind: ComponentKind::Weapon {
+                        fire_rate: 20.,
+                        projectile_type: ProjectileType::Missile,
+                        projectile_speed: 30.,
+                        launch_point: vec2(1.48, 0.),
+                        max_angle: PI / 2.,
+                        shot_energy: 20.,
+                        range: 300.,
+                        turn_rate: 1.5,
+                        spread: 0.,
+                        magazine_size: 4,
+                        reload_time: 60.,
+                    },
+                },
+            ),
+            (
+                ComponentType::FUEL_TANK,
+                ComponentDefinition {
+                    name: "FuelTank".to_string(),
+                    mass: 5.,
+                    health: 25.,
+                    damage_absorption: 0.6,
+                    scale: uvec2(2, 1),
+                    top: None,
+                    build_time: 1.5,
+                    materials: BTreeMap::from([
+                        (Material::Iron, 6.),
+                        (Material::Nickel, 4.),
+                        (Material::Carbon, 2.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::FuelTank {
+                        capacity: 1000000.,
+                        fuel_density: 0.001,
+                        leak_rate: 0.05,
+                    },
+                },
+            ),
+            (
+                ComponentType::REACTOR,
+                ComponentDefinition {
+                    name: "Reactor".to_string(),
+                    mass: 30.,
+                    health: 40.,
+                    damage_absorption: 0.7,
+                    scale: uvec2(2, 2),
+                    top: None,
+                    build_time: 4.,
+                    materials: BTreeMap::from([
+                        (Material::Iron, 10.),
+                        (Material::Nickel, 10.),
+                        (Material::Copper, 10.),
+                        (Material::Silicates, 5.),
+                        (Material::Carbon, 5.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::Reactor { output: 60. },
+                },
+            ),
+            (
+                ComponentType::BATTERY,
+                ComponentDefinition {
+                    name: "Battery".to_string(),
+                    mass: 10.,
+                    health: 20.,
+                    damage_absorption: 0.5,
+                    scale: uvec2(1, 1),
+                    top: None,
+                    build_time: 1.5,
+                    materials: BTreeMap::from([
+                        (Material::Copper, 6.),
+                        (Material::Nickel, 3.),
+                        (Material::Silicates, 3.),
+                    ]),
+                    power_priority: 0,
+                    kind: ComponentKind::Battery { capacity: 400. },
+                },
+            ),
+            (
+                ComponentType::SHIELD_GENERATOR,
+                ComponentDefinition {
+                    name: "ShieldGenerator".to_string(),
+                    mass: 15.,
+                    health: 25.,
+                    damage_absorption: 0.5,
+                    scale: uvec2(1, 1),
+                    top: None,
+                    build_time: 3.,
+                    materials: BTreeMap::from([
+                        (Material::Copper, 8.),
+                        (Material::Silicates, 6.),
+                        (Material::Carbon, 4.),
+                        (Material::Nickel, 2.),
+                    ]),
+                    power_priority: 2,
+                    kind: ComponentKind::ShieldGenerator {
+                        max_strength: 60.,
+                        recharge_rate: 4.,
+                        arc: PI / 2.,
+                        radius: 4.,
+                        recharge_energy: 2.,
+                    },
+                },
+            ),
+            (
+                ComponentType::LASER_WEAPON,
+                ComponentDefinition {
+                    name: "LaserWeapon".to_string(),
+                    mass: 15.,
+                    health: 20.,
+                    damage_absorption: 0.3,
+                    scale: uvec2(1, 1),
+                    top: Some(TopComponentProperties {
+                        occupies: vec![ivec2(0, 0)],
+                    }),
+                    build_time: 3.,
+                    materials: BTreeMap::from([
+                        (Material::Copper, 10.),
+                        (Material::Silicates, 8.),
+                        (Material::Carbon, 3.),
+                        (Material::Iron, 2.),
+                    ]),
+                    power_priority: 1,
+                    kind: ComponentKind::LaserWeapon {
+                        range: 60.,
+                        damage_rate: 30.,
+                        launch_point: vec2(0.48, 0.),
+                        max_angle: PI,
+                        energy_consumption: 15.,
+                    },
+                },
+            ),
+        ]);
+        Self {
+            version: COMPONENT_CATALOGUE_VERSION,
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::stable_hash;
+
+    #[test]
+    fn default_catalogue_roundtrip() {
+        let catalogue = ComponentCatalogue::default();
+        catalogue.validate().unwrap();
+
+        let loaded = ComponentCatalogue::load(&serialize_str(&catalogue).unwrap()).unwrap();
+        assert_eq!(stable_hash(&loaded), stable_hash(&catalogue));
+    }
+
+    #[test]
+    fn invalid_catalogues_are_rejected() {
+        let mut catalogue = ComponentCatalogue::default();
+        catalogue.version += 1;
+        assert!(matches!(
+            catalogue.validate(),
+            Err(CatalogueError::UnsupportedVersion(_))
+        ));
+
+        let mut catalogue = ComponentCatalogue::default();
+        catalogue.components.remove(&ComponentType::CENTRAL);
+        assert!(matches!(
+            catalogue.validate(),
+            Err(CatalogueError::InvalidCentral)
+        ));
+
+        let mut catalogue = ComponentCatalogue::default();
+        catalogue
+            .components
+            .get_mut(&ComponentType::STEEL_BLOCK)
+            .unwrap()
+            .damage_absorption = 2.;
+        assert!(matches!(
+            catalogue.validate(),
+            Err(CatalogueError::InvalidValue(ComponentType::STEEL_BLOCK, _))
+        ));
+
+        let mut catalogue = ComponentCatalogue::default();
+        catalogue
+            .components
+            .get_mut(&ComponentType::REACTOR)
+            .unwrap()
+            .mass = 0.;
+        assert!(matches!(
+            catalogue.validate(),
+            Err(CatalogueError::InvalidValue(ComponentType::REACTOR, _))
+        ));
+
+        let mut catalogue = ComponentCatalogue::default();
+        let weapon = catalogue
+            .components
+            .get_mut(&ComponentType::KINETIC_WEAPON)
+            .unwrap();
+        if let ComponentKind::Weapon { fire_rate, .. } = &mut weapon.kind {
+            *fire_rate = 1e-40;
+        }
+        assert!(matches!(
+            catalogue.validate(),
+            Err(CatalogueError::InvalidValue(
+                ComponentType::KINETIC_WEAPON,
+                _
+            ))
+        ));
+
+        assert!(matches!(
+            ComponentCatalogue::load("version: 1\ncomponents: [1, 2]"),
+            Err(CatalogueError::InvalidFormat(_))
+        ));
+    }
+}
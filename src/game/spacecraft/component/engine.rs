@@ -1,17 +1,27 @@
 use super::*;
 
+/// Largest angle the engine can turn its thrust away from its orientation
+pub const MAX_GIMBAL: f32 = 1.2;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Engine {
     mass: f32,
     health: f32,
     pub body: ComponentBody,
+    /// Reserve of the engine itself, refilled from the fuel tanks of the spacecraft
     pub fuel: f32,
+    pub fuel_capacity: f32,
     pub active: bool,
     pub thrust: f32,
     rotation: f32,
     fuel_density: f32,
     pub power: f32,
     pub ignition_point: Vec2,
+    /// Energy used per second at full power
+    pub energy_consumption: f32,
+    /// Energy supplied by the power grid for the current update
+    pub energy: f32,
+    pub priority: u8,
 }
 
 impl Engine {
@@ -29,14 +39,23 @@ impl Engine {
             health,
             body,
             fuel,
+            fuel_capacity: fuel,
             active: false,
             thrust,
             rotation: 0.,
             fuel_density,
             power: 1.,
             ignition_point,
+            energy_consumption: 0.,
+            energy: 0.,
+            priority: 0,
         }
     }
+    pub fn with_power_usage(mut self, energy_consumption: f32, priority: u8) -> Self {
+        self.energy_consumption = energy_consumption;
+        self.priority = priority;
+        self
+    }
 }
 
 impl Engine {
@@ -47,8 +66,15 @@ impl Engine {
     pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
         let mut result = vec![];
         let dt = time - self.body.cur_time;
+        let demand = self.energy_demand(time);
+        let efficiency = if demand > 0. {
+            (self.energy / demand).min(1.)
+        } else {
+            1.
+        };
+        self.energy = 0.;
         if self.active && self.fuel > 0. {
-            let thrust = self.fuel.min(self.thrust * dt * self.power);
+            let thrust = self.fuel.min(self.thrust * dt * self.power * efficiency);
             self.fuel -= thrust;
 
             let force_vector = Vec2::from_angle(self.rotation + self.body.orientation.to_radians())
@@ -58,8 +84,20 @@ impl Engine {
         }
         result
     }
+    pub fn energy_demand(&self, time: f32) -> f32 {
+        if !self.active || self.fuel <= 0. {
+            return 0.;
+        }
+        self.energy_consumption * self.power * (time - self.body.cur_time)
+    }
     pub fn mass(&self) -> f32 {
-        self.fuel * self.fuel_density
+        self.mass + self.fuel * self.fuel_density
+    }
+    /// Adds up to `amount` of fuel to the reserve and returns how much fit in
+    pub fn refuel(&mut self, amount: f32) -> f32 {
+        let added = (self.fuel_capacity - self.fuel).min(amount).max(0.);
+        self.fuel += added;
+        added
     }
     pub fn health(&self) -> f32 {
         self.health
@@ -74,8 +112,12 @@ impl Engine {
             }
             ComponentCmd::SetRotation(rotation) => {
                 let rotation = normalize_radians(rotation);
-                self.rotation = rotation.min(1.2).max(-1.2);
+                self.rotation = rotation.clamp(-MAX_GIMBAL, MAX_GIMBAL);
+            }
+            ComponentCmd::SetPriority(priority) => {
+                self.priority = priority;
             }
+            ComponentCmd::SetTarget(_) | ComponentCmd::SetTargetingMode(_) => {}
             ComponentCmd::SelfDestruct => {
                 self.health = 0.;
             }
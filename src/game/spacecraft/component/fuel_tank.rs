@@ -0,0 +1,73 @@
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FuelTank {
+    pub body: ComponentBody,
+    mass: f32,
+    health: f32,
+    pub fuel: f32,
+    pub capacity: f32,
+    fuel_density: f32,
+    /// Fraction of the capacity lost per second when the tank is at zero health,
+    /// the leak grows linearly with the damage taken
+    leak_rate: f32,
+}
+
+impl FuelTank {
+    pub fn new(
+        body: ComponentBody,
+        mass: f32,
+        health: f32,
+        capacity: f32,
+        fuel_density: f32,
+        leak_rate: f32,
+    ) -> Self {
+        Self {
+            body,
+            mass,
+            health,
+            fuel: capacity,
+            capacity,
+            fuel_density,
+            leak_rate,
+        }
+    }
+}
+
+impl FuelTank {
+    pub fn body(&self) -> &ComponentBody {
+        &self.body
+    }
+    pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
+        let dt = time - self.body.cur_time;
+        let damage = (1. - self.health / self.body.max_health).clamp(0., 1.);
+        self.fuel = (self.fuel - self.capacity * self.leak_rate * damage * dt).max(0.);
+        vec![]
+    }
+    pub fn mass(&self) -> f32 {
+        self.mass + self.fuel * self.fuel_density
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    /// Removes up to `amount` of fuel and returns how much was taken
+    pub fn take_fuel(&mut self, amount: f32) -> f32 {
+        let taken = self.fuel.min(amount).max(0.);
+        self.fuel -= taken;
+        taken
+    }
+    /// Adds up to `amount` of fuel and returns how much fit into the tank
+    pub fn refuel(&mut self, amount: f32) -> f32 {
+        let added = (self.capacity - self.fuel).min(amount).max(0.);
+        self.fuel += added;
+        added
+    }
+    pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
+        if let ComponentCmd::SelfDestruct = cmd {
+            self.health = 0.;
+        }
+    }
+    pub fn apply_damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+}
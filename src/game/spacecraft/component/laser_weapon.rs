@@ -0,0 +1,112 @@
+use super::*;
+
+/// Hitscan weapon, while active and powered it damages the first object along its beam every tick
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LaserWeapon {
+    pub body: ComponentBody,
+    mass: f32,
+    health: f32,
+    pub active: bool,
+    pub rotation: f32,
+    max_angle: f32,
+    launch_point: Vec2,
+    pub range: f32,
+    /// Damage per second dealt at the hit point
+    pub damage_rate: f32,
+    /// Energy per second while firing
+    pub energy_consumption: f32,
+    /// Energy supplied by the power grid for the current update
+    pub energy: f32,
+    pub priority: u8,
+}
+
+impl LaserWeapon {
+    pub fn new(
+        body: ComponentBody,
+        mass: f32,
+        health: f32,
+        range: f32,
+        damage_rate: f32,
+        launch_point: Vec2,
+        max_angle: f32,
+    ) -> Self {
+        Self {
+            body,
+            mass,
+            health,
+            active: false,
+            rotation: 0.,
+            max_angle,
+            launch_point,
+            range,
+            damage_rate,
+            energy_consumption: 0.,
+            energy: 0.,
+            priority: 0,
+        }
+    }
+    pub fn with_power_usage(mut self, energy_consumption: f32, priority: u8) -> Self {
+        self.energy_consumption = energy_consumption;
+        self.priority = priority;
+        self
+    }
+}
+
+impl LaserWeapon {
+    pub fn body(&self) -> &ComponentBody {
+        &self.body
+    }
+    pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
+        let dt = time - self.body.cur_time;
+        let demand = self.energy_demand(time);
+        let efficiency = if demand > 0. {
+            (self.energy / demand).min(1.)
+        } else {
+            1.
+        };
+        self.energy = 0.;
+        if !self.active || dt <= 0. || efficiency <= 0. {
+            return vec![];
+        }
+        let beam_rotation = self.body.orientation.to_radians() + self.rotation;
+        vec![ComponentEffect::FireBeam(
+            self.body.position.as_vec2() + self.launch_point.rotate_rad(beam_rotation),
+            beam_rotation,
+            self.range,
+            self.damage_rate * dt * efficiency,
+        )]
+    }
+    pub fn energy_demand(&self, time: f32) -> f32 {
+        if !self.active {
+            return 0.;
+        }
+        self.energy_consumption * (time - self.body.cur_time)
+    }
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
+        match cmd {
+            ComponentCmd::SetActive(active) => {
+                self.active = active;
+            }
+            ComponentCmd::SetRotation(rotation) => {
+                let rotation = normalize_radians(rotation);
+                self.rotation = rotation.clamp(-self.max_angle, self.max_angle);
+            }
+            ComponentCmd::SetPriority(priority) => {
+                self.priority = priority;
+            }
+            ComponentCmd::SelfDestruct => {
+                self.health = 0.;
+            }
+            _ => {}
+        }
+    }
+    pub fn apply_damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Reactor {
+    pub body: ComponentBody,
+    mass: f32,
+    health: f32,
+    /// Energy produced per second at full power
+    pub output: f32,
+    pub active: bool,
+    pub power: f32,
+}
+
+impl Reactor {
+    pub fn new(body: ComponentBody, mass: f32, health: f32, output: f32) -> Self {
+        Self {
+            body,
+            mass,
+            health,
+            output,
+            active: true,
+            power: 1.,
+        }
+    }
+}
+
+impl Reactor {
+    pub fn body(&self) -> &ComponentBody {
+        &self.body
+    }
+    pub fn update(&mut self, _time: f32) -> Vec<ComponentEffect> {
+        vec![]
+    }
+    /// Energy produced until `time`
+    pub fn production(&self, time: f32) -> f32 {
+        if !self.active {
+            return 0.;
+        }
+        self.output * self.power * (time - self.body.cur_time)
+    }
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
+        match cmd {
+            ComponentCmd::SetActive(active) => {
+                self.active = active;
+            }
+            ComponentCmd::SetPower(power) => {
+                self.power = power.clamp(0., 1.);
+            }
+            ComponentCmd::SelfDestruct => {
+                self.health = 0.;
+            }
+            _ => {}
+        }
+    }
+    pub fn apply_damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+}
@@ -0,0 +1,132 @@
+use super::*;
+
+/// Projects a shield over the hull within `radius` grid cells of the generator and `arc`
+/// radians to both sides of its facing, the shield absorbs damage until its strength runs out
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShieldGenerator {
+    pub body: ComponentBody,
+    mass: f32,
+    health: f32,
+    pub active: bool,
+    pub strength: f32,
+    pub max_strength: f32,
+    /// Strength regained per second
+    pub recharge_rate: f32,
+    pub arc: f32,
+    pub radius: f32,
+    /// Rotation of the shield relative to the orientation of the component
+    pub rotation: f32,
+    /// Energy needed per point of strength recharged
+    pub recharge_energy: f32,
+    /// Energy supplied by the power grid for the current update
+    pub energy: f32,
+    pub priority: u8,
+}
+
+impl ShieldGenerator {
+    pub fn new(
+        body: ComponentBody,
+        mass: f32,
+        health: f32,
+        max_strength: f32,
+        recharge_rate: f32,
+        arc: f32,
+        radius: f32,
+    ) -> Self {
+        Self {
+            body,
+            mass,
+            health,
+            active: true,
+            strength: max_strength,
+            max_strength,
+            recharge_rate,
+            arc,
+            radius,
+            rotation: 0.,
+            recharge_energy: 0.,
+            energy: 0.,
+            priority: 0,
+        }
+    }
+    pub fn with_power_usage(mut self, recharge_energy: f32, priority: u8) -> Self {
+        self.recharge_energy = recharge_energy;
+        self.priority = priority;
+        self
+    }
+}
+
+impl ShieldGenerator {
+    pub fn body(&self) -> &ComponentBody {
+        &self.body
+    }
+    pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
+        let recharge = self.recharge(time);
+        let recharge = if self.recharge_energy > 0. {
+            recharge.min(self.energy / self.recharge_energy)
+        } else {
+            recharge
+        };
+        self.energy = 0.;
+        self.strength = (self.strength + recharge).min(self.max_strength);
+        vec![]
+    }
+    /// Strength the shield can regain until `time`
+    fn recharge(&self, time: f32) -> f32 {
+        if !self.active {
+            return 0.;
+        }
+        (self.recharge_rate * (time - self.body.cur_time)).min(self.max_strength - self.strength)
+    }
+    pub fn energy_demand(&self, time: f32) -> f32 {
+        self.recharge(time) * self.recharge_energy
+    }
+    /// Direction the shield faces in the grid coordinates of the spacecraft
+    pub fn facing(&self) -> f32 {
+        self.body.orientation.to_radians() + self.rotation
+    }
+    /// Whether a hit at the grid position `hit` is covered by the shield
+    pub fn covers(&self, hit: Vec2) -> bool {
+        if !self.active || self.strength <= 0. {
+            return false;
+        }
+        let offset = hit - self.body.centered_position();
+        if offset.length() > self.radius {
+            return false;
+        }
+        offset.length() < 0.5
+            || Vec2::from_angle(self.facing()).angle_between(offset).abs() <= self.arc
+    }
+    /// Absorbs up to `damage` and returns how much was absorbed
+    pub fn absorb(&mut self, damage: f32) -> f32 {
+        let absorbed = self.strength.min(damage).max(0.);
+        self.strength -= absorbed;
+        absorbed
+    }
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn handle_cmd(&mut self, cmd: ComponentCmd) {
+        match cmd {
+            ComponentCmd::SetActive(active) => {
+                self.active = active;
+            }
+            ComponentCmd::SetRotation(rotation) => {
+                self.rotation = normalize_radians(rotation);
+            }
+            ComponentCmd::SetPriority(priority) => {
+                self.priority = priority;
+            }
+            ComponentCmd::SelfDestruct => {
+                self.health = 0.;
+            }
+            _ => {}
+        }
+    }
+    pub fn apply_damage(&mut self, damage: f32) {
+        self.health -= damage;
+    }
+}
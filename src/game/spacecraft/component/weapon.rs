@@ -1,10 +1,21 @@
 use super::*;
+use crate::game::stable_hash;
+
+/// Largest difference between the rotation and the firing solution at which an auto-targeting
+/// weapon still fires
+const AIM_TOLERANCE: f32 = 0.02;
+/// Most shots fired within one update, the rest of a long update is lost
+const MAX_SHOTS_PER_UPDATE: usize = 64;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Weapon {
     pub rotation: f32,
+    /// Rotation the weapon turns to in the manual targeting mode
+    pub aim: f32,
+    /// Seconds between two shots
     fire_rate: f32,
-    fire_rate_timer: f32,
+    /// Seconds until the next shot is ready, it never drops below zero while the weapon waits
+    cooldown: f32,
     pub projectile_speed: f32,
     projectile_type: ProjectileType,
     pub body: ComponentBody,
@@ -13,6 +24,29 @@ pub struct Weapon {
     launch_point: Vec2,
     pub active: bool,
     max_angle: f32,
+    /// Energy needed for every shot
+    pub shot_energy: f32,
+    /// Energy stored for the next shots
+    pub charge: f32,
+    pub priority: u8,
+    pub target: Option<Target>,
+    pub targeting_mode: TargetingMode,
+    /// Reach of the auto-targeting
+    pub range: f32,
+    /// Angular speed of the turret, zero for weapons which can't turn
+    pub turn_rate: f32,
+    /// Rotation hitting the current target, computed by the game every tick
+    #[serde(default)]
+    pub firing_solution: Option<f32>,
+    /// Largest deviation of a shot from the rotation
+    pub spread: f32,
+    /// Shots per magazine, zero for weapons which don't need reloading
+    pub magazine_size: u32,
+    /// Shots left in the magazine
+    pub ammo: u32,
+    pub reload_time: f32,
+    /// Shots fired so far, it seeds the spread so the simulation stays deterministic
+    shots_fired: u32,
 }
 
 impl Weapon {
@@ -28,8 +62,9 @@ impl Weapon {
     ) -> Self {
         Self {
             rotation: 0.,
+            aim: 0.,
             fire_rate,
-            fire_rate_timer: 0.,
+            cooldown: 0.,
             projectile_speed,
             projectile_type,
             body,
@@ -38,7 +73,93 @@ impl Weapon {
             launch_point,
             active: false,
             max_angle,
+            shot_energy: 0.,
+            charge: 0.,
+            priority: 0,
+            target: None,
+            targeting_mode: TargetingMode::Manual,
+            range: 0.,
+            turn_rate: 0.,
+            firing_solution: None,
+            spread: 0.,
+            magazine_size: 0,
+            ammo: 0,
+            reload_time: 0.,
+            shots_fired: 0,
+        }
+    }
+    pub fn with_targeting(mut self, range: f32, turn_rate: f32) -> Self {
+        self.range = range;
+        self.turn_rate = turn_rate;
+        self
+    }
+    pub fn with_power_usage(mut self, shot_energy: f32, priority: u8) -> Self {
+        self.shot_energy = shot_energy;
+        self.priority = priority;
+        self
+    }
+    pub fn with_ammunition(mut self, magazine_size: u32, reload_time: f32, spread: f32) -> Self {
+        self.magazine_size = magazine_size;
+        self.ammo = magazine_size;
+        self.reload_time = reload_time;
+        self.spread = spread;
+        self
+    }
+    /// Energy for all the shots which get ready until `time`
+    pub fn energy_demand(&self, time: f32) -> f32 {
+        if !self.active {
+            return 0.;
+        }
+        let shots = self.shots_ready(time - self.body.cur_time);
+        (shots as f32 * self.shot_energy - self.charge).max(0.)
+    }
+    /// Number of shots the cooldown and reloading allow within `dt`, follows `update`
+    fn shots_ready(&self, dt: f32) -> u32 {
+        let mut cooldown = self.cooldown - dt;
+        let mut ammo = self.ammo;
+        let mut shots = 0;
+        while cooldown <= 0. && shots < MAX_SHOTS_PER_UPDATE as u32 {
+            let age = -cooldown;
+            shots += 1;
+            cooldown += self.fire_rate;
+            if self.magazine_size > 0 {
+                ammo = if ammo == 0 { self.magazine_size } else { ammo } - 1;
+                if ammo == 0 {
+                    cooldown = cooldown.max(self.reload_time - age);
+                }
+            }
+        }
+        shots
+    }
+    pub fn max_angle(&self) -> f32 {
+        self.max_angle
+    }
+    pub fn reloading(&self) -> bool {
+        self.magazine_size > 0 && self.ammo == 0
+    }
+    /// Turns towards the aim or the firing solution, returns whether the weapon is aimed
+    /// well enough to fire
+    fn track(&mut self, dt: f32) -> bool {
+        let aim = match self.targeting_mode {
+            TargetingMode::Manual => Some(self.aim),
+            _ => self.firing_solution,
+        };
+        let Some(aim) = aim else {
+            return false;
+        };
+        let max_turn = self.turn_rate * dt;
+        let turn = normalize_radians(aim - self.rotation).clamp(-max_turn, max_turn);
+        self.rotation = (self.rotation + turn).clamp(-self.max_angle, self.max_angle);
+        self.targeting_mode == TargetingMode::Manual
+            || normalize_radians(aim - self.rotation).abs() <= AIM_TOLERANCE
+    }
+    /// Deviation of the next shot, drawn from [-spread, spread]
+    fn deviation(&self) -> f32 {
+        if self.spread <= 0. {
+            return 0.;
         }
+        let hash = stable_hash(&(self.shots_fired, self.body.position));
+        ((hash >> 40) as f32 / (1u64 << 24) as f32 * 2. - 1.) * self.spread
     }
 }
 
@@ -46,25 +167,49 @@ impl Weapon {
     pub fn body(&self) -> &ComponentBody {
         &self.body
     }
+    /// Fires every shot which got ready during the update, independently of the tick rate.
+    /// Projectiles fired before the end of the update are moved by the distance they have flown
+    /// since, relative to the spacecraft.
     pub fn update(&mut self, time: f32) -> Vec<ComponentEffect> {
         let dt = time - self.body.cur_time;
 
         let mut effects = vec![];
 
-        self.fire_rate_timer += dt;
-        if self.active {
-            if self.fire_rate_timer > self.fire_rate {
-                self.fire_rate_timer = 0.;
-                let projectile_rotation = self.body.orientation.to_radians() + self.rotation;
-                effects.push(ComponentEffect::CreateProjectile(
-                    self.projectile_type,
-                    self.body.position.as_vec2()
-                        + self.launch_point.rotate_rad(projectile_rotation),
-                    Vec2::from_angle(projectile_rotation) * self.projectile_speed,
-                    projectile_rotation,
-                ));
+        let aimed = self.track(dt);
+        self.cooldown -= dt;
+        while self.cooldown <= 0. && effects.len() < MAX_SHOTS_PER_UPDATE {
+            if self.reloading() {
+                self.ammo = self.magazine_size;
+            }
+            if !self.active || !aimed || self.charge < self.shot_energy {
+                break;
+            }
+            // time which passed between the shot and the end of the update
+            let age = -self.cooldown;
+            self.charge -= self.shot_energy;
+            let projectile_rotation =
+                self.body.orientation.to_radians() + self.rotation + self.deviation();
+            let velocity = Vec2::from_angle(projectile_rotation) * self.projectile_speed;
+            effects.push(ComponentEffect::CreateProjectile(
+                self.projectile_type,
+                self.body.position.as_vec2()
+                    + self.launch_point.rotate_rad(projectile_rotation)
+                    + velocity * age,
+                velocity,
+                projectile_rotation,
+                self.target,
+            ));
+            self.shots_fired = self.shots_fired.wrapping_add(1);
+            self.cooldown += self.fire_rate;
+
+            if self.magazine_size > 0 {
+                self.ammo -= 1;
+                if self.ammo == 0 {
+                    self.cooldown = self.cooldown.max(self.reload_time - age);
+                }
             }
         }
+        self.cooldown = self.cooldown.max(0.);
 
         effects
     }
@@ -72,7 +217,7 @@ impl Weapon {
         match cmd {
             ComponentCmd::SetRotation(rotation) => {
                 let rotation = normalize_radians(rotation);
-                self.rotation = rotation.min(self.max_angle).max(-self.max_angle);
+                self.aim = rotation.clamp(-self.max_angle, self.max_angle);
             }
             ComponentCmd::SelfDestruct => {
                 self.health = 0.;
@@ -80,6 +225,16 @@ impl Weapon {
             ComponentCmd::SetActive(active) => {
                 self.active = active;
             }
+            ComponentCmd::SetPriority(priority) => {
+                self.priority = priority;
+            }
+            ComponentCmd::SetTarget(target) => {
+                self.target = target;
+            }
+            ComponentCmd::SetTargetingMode(targeting_mode) => {
+                self.targeting_mode = targeting_mode;
+                self.firing_solution = None;
+            }
             _ => {}
         }
     }
@@ -87,3 +242,48 @@ impl Weapon {
         self.health -= damage;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shots_fired(weapon: &Weapon, duration: f32, dt: f32) -> usize {
+        let mut weapon = weapon.clone();
+        weapon.handle_cmd(ComponentCmd::SetActive(true));
+        let mut shots = 0;
+        let mut time = 0.;
+        while time + dt <= duration + 1e-4 {
+            time += dt;
+            weapon.charge += weapon.energy_demand(time);
+            shots += weapon.update(time).len();
+            weapon.body.update(time);
+        }
+        shots
+    }
+
+    #[test]
+    fn fire_rate_does_not_depend_on_tick_rate() {
+        let Some(Component::Weapon(mut weapon)) = ComponentCatalogue::default().build(
+            ComponentType::KINETIC_WEAPON,
+            IVec2::ZERO,
+            Orientation::Right,
+        ) else {
+            unreachable!()
+        };
+        weapon.fire_rate = 0.15;
+        for dt in [0.01, 0.05, 0.25, 1.] {
+            assert_eq!(shots_fired(&weapon, 2., dt), 14, "dt {}", dt);
+        }
+
+        // a long update fires a bounded number of shots
+        let mut rapid = weapon.clone();
+        rapid.fire_rate = MIN_FIRE_RATE;
+        assert_eq!(shots_fired(&rapid, 100., 100.), MAX_SHOTS_PER_UPDATE);
+
+        // 4 shots per magazine and 0.5 s to reload
+        let weapon = weapon.with_ammunition(4, 0.5, 0.);
+        for dt in [0.01, 0.05, 0.25, 1.] {
+            assert_eq!(shots_fired(&weapon, 2., dt), 9, "dt {}", dt);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use super::*;
+
+/// Angular acceleration per radian of heading error
+const HEADING_GAIN: f32 = 4.;
+/// Angular acceleration per radian per second of angular velocity, critically damps the heading
+const ROTATION_DAMPING: f32 = 4.;
+/// Linear acceleration per unit of velocity error
+const VELOCITY_GAIN: f32 = 2.;
+/// Fraction of the full thrust planned for braking when approaching a point
+const BRAKING_MARGIN: f32 = 0.5;
+/// Gimbal angles tried for every engine
+const GIMBAL_STEPS: usize = 9;
+const ALLOCATION_ITERATIONS: usize = 40;
+
+/// High level goal the flight controller steers the spacecraft to
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq)]
+pub enum FlightGoal {
+    /// Turns to the world rotation and stops rotating
+    Heading(f32),
+    /// Matches the world velocity without rotating
+    Velocity(Vec2),
+    /// Flies to the world position and stops there
+    Point(Vec2),
+    /// Stops moving and rotating
+    Stop,
+}
+
+/// Flies the spacecraft by driving the activation, power and gimbal of its engines
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct FlightController {
+    pub goal: Option<FlightGoal>,
+}
+
+/// Engine as seen by the thrust allocation, in grid coordinates
+pub(super) struct EngineMount {
+    pub id: ComponentId,
+    /// Position relative to the center of mass
+    pub arm: Vec2,
+    pub orientation: f32,
+    pub thrust: f32,
+}
+
+/// Engine commands found by `allocate_thrust` and the linear acceleration they produce
+pub(super) struct ThrustAllocation {
+    /// Engine, gimbal and power of every engine which fires
+    pub commands: Vec<(ComponentId, f32, f32)>,
+    pub linear: Vec2,
+}
+
+/// Engines of the components which still have fuel
+pub(super) fn engine_mounts(
+    components: &BTreeMap<ComponentId, Component>,
+    center_of_mass: Vec2,
+) -> Vec<EngineMount> {
+    components
+        .iter()
+        .filter_map(|(id, component)| match component {
+            Component::Engine(engine) if engine.fuel > 0. => Some(EngineMount {
+                id: *id,
+                arm: engine.body.centered_position() - center_of_mass,
+                orientation: engine.body.orientation.to_radians(),
+                thrust: engine.thrust,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Finds the engine commands whose combined acceleration comes as close as possible to `target`,
+/// a linear acceleration in grid coordinates and an angular acceleration. Every engine takes the
+/// gimbal which contributes the most, then the powers are found by projected gradient descent on
+/// the squared acceleration error, with the angular part weighted by `angular_weight`.
+pub(super) fn allocate_thrust(
+    engines: &[EngineMount],
+    mass: f32,
+    inertia: f32,
+    target: (Vec2, f32),
+    angular_weight: f32,
+) -> ThrustAllocation {
+    let mass = mass.max(f32::EPSILON);
+    let inertia = inertia.max(f32::EPSILON) / angular_weight;
+    let target = (target.0, target.1 * angular_weight);
+
+    // gimbal, linear and weighted angular acceleration at full power
+    let mut choices = vec![];
+    for engine in engines {
+        let best = (0..GIMBAL_STEPS)
+            .map(|step| {
+                let rotation = MAX_GIMBAL * (2. * step as f32 / (GIMBAL_STEPS - 1) as f32 - 1.);
+                let force = Vec2::from_angle(engine.orientation + rotation) * engine.thrust;
+                (rotation, force / mass, engine.arm.perp_dot(force) / inertia)
+            })
+            .max_by(|a, b| {
+                let contribution = |x: &(f32, Vec2, f32)| x.1.dot(target.0) + x.2 * target.1;
+                contribution(a).partial_cmp(&contribution(b)).unwrap()
+            });
+        if let Some(choice) = best {
+            if choice.1.dot(target.0) + choice.2 * target.1 > 0. {
+                choices.push((engine.id, choice));
+            }
+        }
+    }
+
+    let lipschitz = choices
+        .iter()
+        .map(|(_, (_, linear, angular))| linear.length_squared() + angular.powi(2))
+        .sum::<f32>();
+    let mut powers = vec![0.; choices.len()];
+    let accelerations = |powers: &[f32]| {
+        let (mut linear, mut angular) = (Vec2::ZERO, 0.);
+        for ((_, choice), power) in choices.iter().zip(powers) {
+            linear += choice.1 * *power;
+            angular += choice.2 * *power;
+        }
+        (linear, angular)
+    };
+    if lipschitz > 0. {
+        for _ in 0..ALLOCATION_ITERATIONS {
+            let (linear, angular) = accelerations(&powers);
+            let (linear_error, angular_error) = (linear - target.0, angular - target.1);
+            for ((_, choice), power) in choices.iter().zip(&mut powers) {
+                let gradient = choice.1.dot(linear_error) + choice.2 * angular_error;
+                *power = (*power - gradient / lipschitz).clamp(0., 1.);
+            }
+        }
+    }
+
+    let (linear, _) = accelerations(&powers);
+    ThrustAllocation {
+        commands: choices
+            .iter()
+            .zip(powers)
+            .filter(|(_, power)| *power > 0.)
+            .map(|((id, (rotation, _, _)), power)| (*id, *rotation, power))
+            .collect(),
+        linear,
+    }
+}
+
+impl Spacecraft {
+    pub fn set_flight_goal(&mut self, goal: FlightGoal) {
+        self.flight_controller.goal = Some(goal);
+    }
+    /// Hands the engines back to the pilot, all of them are shut down
+    pub fn disengage_flight_controller(&mut self) {
+        self.flight_controller.goal = None;
+        for engine_id in self.engine_ids() {
+            self.execute_component_cmd(engine_id, ComponentCmd::SetActive(false));
+        }
+    }
+    fn engine_ids(&self) -> Vec<ComponentId> {
+        self.components
+            .iter()
+            .filter(|(_, component)| matches!(component, Component::Engine(_)))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+    /// Linear acceleration in world coordinates and angular acceleration needed for the goal
+    fn desired_acceleration(&self, goal: FlightGoal) -> (Vec2, f32) {
+        let body = &self.body;
+        let hold_rotation = -body.angular_velocity * ROTATION_DAMPING;
+        let match_velocity = |velocity: Vec2| (velocity - body.velocity) * VELOCITY_GAIN;
+        match goal {
+            FlightGoal::Heading(heading) => (
+                Vec2::ZERO,
+                normalize_radians(heading - body.rotation) * HEADING_GAIN + hold_rotation,
+            ),
+            FlightGoal::Velocity(velocity) => (match_velocity(velocity), hold_rotation),
+            FlightGoal::Point(point) => {
+                let offset = point - body.position;
+                let max_acceleration = self
+                    .components
+                    .values()
+                    .map(|component| match component {
+                        Component::Engine(engine) => engine.thrust,
+                        _ => 0.,
+                    })
+                    .sum::<f32>()
+                    / self.mass.max(f32::EPSILON);
+                // the fastest speed from which the spacecraft can still brake in time
+                let speed = (2. * max_acceleration * BRAKING_MARGIN * offset.length()).sqrt();
+                (
+                    match_velocity(offset.normalize_or_zero() * speed),
+                    hold_rotation,
+                )
+            }
+            FlightGoal::Stop => (match_velocity(Vec2::ZERO), hold_rotation),
+        }
+    }
+    /// Commands the engines so their combined force and torque come as close as possible to the
+    /// ones the goal needs
+    pub(super) fn apply_flight_control(&mut self) {
+        let Some(goal) = self.flight_controller.goal else {
+            return;
+        };
+        let (acceleration, angular_acceleration) = self.desired_acceleration(goal);
+        let allocation = allocate_thrust(
+            &engine_mounts(&self.components, self.center_of_mass),
+            self.mass,
+            self.inertia,
+            (
+                acceleration.rotate(Vec2::from_angle(-self.body.rotation)),
+                angular_acceleration,
+            ),
+            1.,
+        );
+
+        for engine_id in self.engine_ids() {
+            self.execute_component_cmd(engine_id, ComponentCmd::SetActive(false));
+        }
+        for (engine_id, rotation, power) in allocation.commands {
+            self.execute_component_cmd(engine_id, ComponentCmd::SetRotation(rotation));
+            self.execute_component_cmd(engine_id, ComponentCmd::SetPower(power));
+            self.execute_component_cmd(engine_id, ComponentCmd::SetActive(true));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    /// Engines pushing forward and backward above and below the center
+    fn spacecraft() -> Spacecraft {
+        SpacecraftBuilder::new()
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, 1), Orientation::Up)
+            .with(ComponentType::STEEL_BLOCK, ivec2(0, -1), Orientation::Up)
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(0, 2),
+                Orientation::Right,
+            )
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(0, -2),
+                Orientation::Right,
+            )
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(-1, 2),
+                Orientation::Left,
+            )
+            .with(
+                ComponentType::RAPTOR_ENGINE,
+                ivec2(-1, -2),
+                Orientation::Left,
+            )
+            .with(ComponentType::REACTOR, ivec2(1, 0), Orientation::Up)
+            .build()
+    }
+
+    fn fly(spacecraft: &mut Spacecraft, goal: FlightGoal, duration: f32) {
+        spacecraft.set_flight_goal(goal);
+        let mut time = spacecraft.body.cur_time;
+        for _ in 0..(duration / 0.05) as usize {
+            time += 0.05;
+            spacecraft.update(time);
+            spacecraft.body.update(time);
+        }
+    }
+
+    #[test]
+    fn holds_heading_and_flies_to_point() {
+        let mut spacecraft = spacecraft();
+        fly(&mut spacecraft, FlightGoal::Heading(PI / 2.), 10.);
+        assert!((spacecraft.body.rotation - PI / 2.).abs() < 0.05);
+        assert!(spacecraft.body.angular_velocity.abs() < 0.05);
+
+        let target = vec2(40., -25.);
+        fly(&mut spacecraft, FlightGoal::Point(target), 30.);
+        assert!(
+            spacecraft.body.position.distance(target) < 1.,
+            "stopped at {}",
+            spacecraft.body.position
+        );
+        assert!(spacecraft.body.velocity.length() < 0.5);
+
+        spacecraft.disengage_flight_controller();
+        assert!(spacecraft
+            .components
+            .values()
+            .all(|component| match component {
+                Component::Engine(engine) => !engine.active,
+                _ => true,
+            }));
+    }
+}
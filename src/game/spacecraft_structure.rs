@@ -1,28 +1,33 @@
 use crate::prelude::*;
 
-use super::spacecraft::Orient;
+use super::spacecraft::{ComponentCatalogue, Orient};
+use super::HullClass;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SpacecraftStructure {
     pub component_placeholders: Vec<ComponentPlaceholder>,
     pub tags: Vec<String>,
+    #[serde(default)]
+    pub hull_class: HullClass,
 }
 
 impl SpacecraftStructure {
     pub fn new() -> Self {
         Self {
             component_placeholders: vec![ComponentPlaceholder::new(
-                ComponentType::Central,
+                ComponentType::CENTRAL,
                 ivec2(0, 0),
                 Orientation::Up,
             )],
             tags: vec![],
+            hull_class: HullClass::default(),
         }
     }
-    pub fn build_time(&self) -> f32 {
+    pub fn build_time(&self, catalogue: &ComponentCatalogue) -> f32 {
         self.component_placeholders
             .iter()
-            .map(|c| c.component_type.build_time())
+            .filter_map(|c| catalogue.get(c.component_type))
+            .map(|definition| definition.build_time)
             .sum()
     }
     /// Checks if the structure is valid. A structure is valid if:
@@ -30,76 +35,159 @@ impl SpacecraftStructure {
     /// - All components are connected
     /// - No components overlap
     /// - All top components are connected to a bottom component
-    /// - No component position coordinate absolute value is higher than 4
-    pub fn valid(&self) -> bool {
-        let mut central = false;
-        let mut top_occupies = BTreeSet::new();
-        let mut bot_occupies = BTreeSet::new();
+    /// - No occupied cell coordinate absolute value is higher than the grid size of its hull class
+    /// - It has no more components of a type than its hull class allows
+    /// - All components are defined in the catalogue
+    pub fn valid(&self, catalogue: &ComponentCatalogue) -> bool {
+        self.validate(catalogue).is_empty()
+    }
+    /// Lists everything which makes the structure invalid, see `valid`
+    pub fn validate(&self, catalogue: &ComponentCatalogue) -> Vec<StructureError> {
+        let mut errors = vec![];
+        let mut central = None;
+        let mut top_occupies = BTreeMap::new();
+        let mut bot_occupies = BTreeMap::new();
+        let mut counts = BTreeMap::new();
+        let grid_size = self.hull_class.grid_size();
 
-        for component_placeholder in &self.component_placeholders {
-            if component_placeholder.position.x.abs() > 4
-                || component_placeholder.position.y.abs() > 4
-            {
-                return false;
-            }
+        for (index, component_placeholder) in self.component_placeholders.iter().enumerate() {
+            let Some(definition) = catalogue.get(component_placeholder.component_type) else {
+                errors.push(StructureError::UnknownComponent(
+                    index,
+                    component_placeholder.component_type,
+                ));
+                continue;
+            };
+            *counts
+                .entry(component_placeholder.component_type)
+                .or_insert(0) += 1;
 
-            if let ComponentType::Central = component_placeholder.component_type {
-                if central {
-                    return false;
+            if component_placeholder.component_type == ComponentType::CENTRAL {
+                if central.is_some() {
+                    errors.push(StructureError::DuplicateCentral(index));
+                } else {
+                    central = Some(index);
                 }
-                central = true;
             }
 
-            if let Some(top) = component_placeholder.component_type.top() {
-                for occupied_pos in &top.occupies {
-                    let offset = occupied_pos.orient(component_placeholder.orientation);
-                    if !top_occupies.insert((
-                        component_placeholder.position.x + offset.x,
-                        component_placeholder.position.y + offset.y,
-                    )) {
-                        return false;
-                    }
+            let (cells, occupies) = match &definition.top {
+                Some(top) => (top.occupies.clone(), &mut top_occupies),
+                None => (
+                    (0..definition.scale.x)
+                        .flat_map(|x| (0..definition.scale.y).map(move |y| uvec2(x, y).as_ivec2()))
+                        .collect(),
+                    &mut bot_occupies,
+                ),
+            };
+            for offset in cells {
+                let cell = component_placeholder.position
+                    + offset.orient(component_placeholder.orientation);
+                if cell.x.abs() > grid_size || cell.y.abs() > grid_size {
+                    errors.push(StructureError::OutOfBounds(index, cell));
                 }
-            } else {
-                for x in 0..component_placeholder.component_type.scale().x {
-                    for y in 0..component_placeholder.component_type.scale().y {
-                        let offset = uvec2(x, y)
-                            .as_ivec2()
-                            .orient(component_placeholder.orientation);
-                        if !bot_occupies.insert((
-                            component_placeholder.position.x + offset.x,
-                            component_placeholder.position.y + offset.y,
-                        )) {
-                            return false;
-                        }
-                    }
+                let occupants: &mut Vec<usize> = occupies.entry((cell.x, cell.y)).or_default();
+                occupants.push(index);
+                if occupants.len() == 2 {
+                    errors.push(StructureError::Overlap(cell));
                 }
             }
         }
 
-        if !central {
-            return false;
+        for (component_type, count) in counts {
+            if self
+                .hull_class
+                .max_components(component_type)
+                .is_some_and(|max| count > max)
+            {
+                errors.push(StructureError::TooManyComponents(component_type, count));
+            }
+        }
+
+        let Some(central) = central else {
+            errors.push(StructureError::MissingCentral);
+            return errors;
+        };
+
+        for ((x, y), indices) in &top_occupies {
+            if !bot_occupies.contains_key(&(*x, *y)) {
+                for index in indices {
+                    errors.push(StructureError::TopWithoutBase(*index, ivec2(*x, *y)));
+                }
+            }
         }
 
-        for top_occupy in top_occupies {
-            if !bot_occupies.contains(&top_occupy) {
-                return false;
+        // flood fill the bottom cells like `Spacecraft::reconstruct`, a top component is
+        // connected through the cell at its position
+        let mut connected = BTreeSet::from([central]);
+        let mut reached = BTreeSet::new();
+        let mut stack = bot_occupies
+            .iter()
+            .filter(|(_, indices)| indices.contains(&central))
+            .map(|(cell, _)| *cell)
+            .collect::<Vec<_>>();
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in [(0, 1), (1, 0), (-1, 0), (0, -1)] {
+                let cell = (x + dx, y + dy);
+                if let Some(indices) = bot_occupies.get(&cell) {
+                    if reached.insert(cell) {
+                        connected.extend(indices);
+                        stack.push(cell);
+                    }
+                }
             }
         }
+        let disconnected = self
+            .component_placeholders
+            .iter()
+            .enumerate()
+            .filter(|(index, component_placeholder)| {
+                let position = component_placeholder.position;
+                let Some(definition) = catalogue.get(component_placeholder.component_type) else {
+                    return false;
+                };
+                !connected.contains(index)
+                    && (definition.top.is_none() || !reached.contains(&(position.x, position.y)))
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if !disconnected.is_empty() {
+            errors.push(StructureError::Disconnected(disconnected));
+        }
 
-        return true;
+        errors
     }
-    pub fn materials(&self) -> BTreeMap<Material, f32> {
+    pub fn materials(&self, catalogue: &ComponentCatalogue) -> BTreeMap<Material, f32> {
         let mut materials = BTreeMap::new();
         for component_placeholder in &self.component_placeholders {
-            for (material, amount) in component_placeholder.component_type.materials() {
-                *materials.entry(material).or_insert(0.) += amount;
+            let Some(definition) = catalogue.get(component_placeholder.component_type) else {
+                continue;
+            };
+            for (material, amount) in &definition.materials {
+                *materials.entry(*material).or_insert(0.) += amount;
             }
         }
         materials
     }
 }
 
+/// Problem of a structure, components are referred to by their index in `component_placeholders`
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum StructureError {
+    MissingCentral,
+    DuplicateCentral(usize),
+    UnknownComponent(usize, ComponentType),
+    /// The cell lies outside of the grid of the hull class
+    OutOfBounds(usize, IVec2),
+    /// The hull class allows fewer components of the type than the structure has
+    TooManyComponents(ComponentType, usize),
+    /// The cell is occupied by more than one component of the same layer
+    Overlap(IVec2),
+    /// The top component occupies a cell without a bottom component under it
+    TopWithoutBase(usize, IVec2),
+    /// Components not connected to the central component
+    Disconnected(Vec<usize>),
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct ComponentPlaceholder {
     pub component_type: ComponentType,
@@ -116,3 +204,66 @@ impl ComponentPlaceholder {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let catalogue = ComponentCatalogue::default();
+        assert!(SpacecraftStructure::new().valid(&catalogue));
+
+        let structure = [
+            (ComponentType::STEEL_BLOCK, ivec2(0, 1)),
+            (ComponentType::STEEL_BLOCK, ivec2(0, 1)),
+            (ComponentType::STEEL_BLOCK, ivec2(3, 3)),
+            (ComponentType::STEEL_BLOCK, ivec2(0, 5)),
+            (ComponentType::KINETIC_WEAPON, ivec2(-3, -3)),
+            (ComponentType::CENTRAL, ivec2(0, -1)),
+        ]
+        .into_iter()
+        .fold(
+            SpacecraftBuilder::new(),
+            |builder, (component_type, position)| {
+                builder.with(component_type, position, Orientation::Up)
+            },
+        )
+        .structure;
+        let errors = structure.validate(&catalogue);
+        assert!(errors.contains(&StructureError::Overlap(ivec2(0, 1))));
+        assert!(errors.contains(&StructureError::OutOfBounds(4, ivec2(0, 5))));
+        assert!(errors.contains(&StructureError::DuplicateCentral(6)));
+        assert!(errors.contains(&StructureError::TopWithoutBase(5, ivec2(-3, -3))));
+        assert!(errors.contains(&StructureError::Disconnected(vec![3, 4, 5])));
+        assert!(!structure.valid(&catalogue));
+    }
+
+    #[test]
+    fn hull_class_bounds_the_structure() {
+        let catalogue = ComponentCatalogue::default();
+        let mut builder = SpacecraftBuilder::new();
+        for y in 1..=6 {
+            builder = builder.with(ComponentType::STEEL_BLOCK, ivec2(0, y), Orientation::Up);
+        }
+        for y in 1..=3 {
+            builder = builder.with(ComponentType::KINETIC_WEAPON, ivec2(0, y), Orientation::Up);
+        }
+        let mut structure = builder.structure;
+        let errors = structure.validate(&catalogue);
+        assert!(errors.contains(&StructureError::OutOfBounds(5, ivec2(0, 5))));
+        assert!(errors.contains(&StructureError::TooManyComponents(
+            ComponentType::KINETIC_WEAPON,
+            3
+        )));
+
+        structure.hull_class = HullClass::Frigate;
+        assert_eq!(structure.validate(&catalogue), vec![]);
+        let star_base = StarBase::new(Vec2::ZERO, Vec2::ZERO, 0., 0);
+        assert!(star_base.can_build_spacecraft(&structure, 0, &catalogue));
+        structure.hull_class = HullClass::Cruiser;
+        assert!(!star_base.can_build_spacecraft(&structure, 0, &catalogue));
+        assert!(star_base.can_build_spacecraft(&structure, 1, &catalogue));
+    }
+}
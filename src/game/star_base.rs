@@ -1,11 +1,18 @@
 mod hangar;
 
-use crate::game::GameObjectBody;
+use crate::game::spacecraft::ComponentCatalogue;
+use crate::game::{GameObjectBody, HullClass};
 use crate::prelude::*;
 use glam::Vec2;
 use hangar::{Hangar, HangarEffect};
 
 const STARBASE_SIZE: f32 = 15.;
+/// Spacecrafts closer than this to the center of their star base can dock
+pub const DOCKING_RANGE: f32 = 30.;
+/// Highest speed relative to the star base at which spacecrafts stay docked
+pub const DOCKING_SPEED: f32 = 2.;
+/// Fuel per second pumped into every docked spacecraft
+pub const REFUEL_RATE: f32 = 100000.;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct StarBase {
@@ -49,21 +56,44 @@ impl StarBase {
             body,
             owner,
             health: 1000.,
-            hangars: vec![Hangar::new(), Hangar::new()],
+            hangars: vec![
+                Hangar::new(HullClass::Frigate.hangar_size()),
+                Hangar::new(HullClass::Cruiser.hangar_size()),
+            ],
         }
     }
     pub fn mass(&self) -> f32 {
         10000.
     }
+    /// Moment of inertia of a square
+    pub fn inertia(&self) -> f32 {
+        self.mass() * (2. * STARBASE_SIZE).powi(2) / 6.
+    }
+    /// Spacecraft of the same owner resting next to the star base
+    pub fn docks(&self, spacecraft: &Spacecraft) -> bool {
+        spacecraft.owner == self.owner
+            && spacecraft.body.position.distance(self.body.position) <= DOCKING_RANGE
+            && spacecraft.body.velocity.distance(self.body.velocity) <= DOCKING_SPEED
+    }
     pub fn can_build_spacecraft(
         &self,
         structure: &SpacecraftStructure,
         hangar_index: usize,
+        catalogue: &ComponentCatalogue,
     ) -> bool {
-        structure.valid() && self.hangars.get(hangar_index).is_some()
+        structure.valid(catalogue)
+            && self
+                .hangars
+                .get(hangar_index)
+                .is_some_and(|hangar| hangar.can_build(structure.hull_class))
     }
-    pub fn build_spacecraft(&mut self, structure: &SpacecraftStructure, hangar_index: usize) {
-        self.hangars[hangar_index].build(structure.clone());
+    pub fn build_spacecraft(
+        &mut self,
+        structure: &SpacecraftStructure,
+        hangar_index: usize,
+        catalogue: &ComponentCatalogue,
+    ) {
+        self.hangars[hangar_index].build(structure.clone(), structure.build_time(catalogue));
     }
     pub fn deploy_spacecraft(&mut self, hangar_index: usize) {
         let Some(hangar) = self.hangars.get_mut(hangar_index) else {
@@ -83,11 +113,11 @@ impl StarBase {
                         spacecraft_transform.angular_velocity = 0.;
                         spacecraft_transform.position.y += 25.;
 
-                        result.push(GameObjectEffect::SpawnSpacecraft(Spacecraft::build(
+                        result.push(GameObjectEffect::BuildSpacecraft(
                             structure,
                             self.owner,
                             spacecraft_transform,
-                        )));
+                        ));
                     }
                 }
             }
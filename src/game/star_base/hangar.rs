@@ -1,23 +1,35 @@
+use crate::game::HullClass;
 use crate::prelude::*;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Hangar {
+    /// Largest `HullClass::hangar_size` the hangar can build
+    #[serde(default = "default_hangar_size")]
+    pub size: u32,
     pub build_speed: f32,
     pub progress: f32,
     pub deploy: bool,
-    pub building_queue: VecDeque<SpacecraftStructure>,
+    pub building_queue: VecDeque<HangarOrder>,
+}
+
+/// Queued structure, its build time is taken from the catalogue when the order is placed
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HangarOrder {
+    pub structure: SpacecraftStructure,
+    pub build_time: f32,
 }
 
 impl std::fmt::Display for Hangar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Hangar: deploy: {}, in queue: {}, progress: {}/{}",
+            "Hangar: size: {}, deploy: {}, in queue: {}, progress: {}/{}",
+            self.size,
             self.deploy,
             self.building_queue.len(),
             self.progress,
-            if let Some(structure) = self.building_queue.front() {
-                structure.build_time()
+            if let Some(order) = self.building_queue.front() {
+                order.build_time
             } else {
                 0.
             }
@@ -25,17 +37,28 @@ impl std::fmt::Display for Hangar {
     }
 }
 
+fn default_hangar_size() -> u32 {
+    1
+}
+
 impl Hangar {
-    pub fn new() -> Self {
+    pub fn new(size: u32) -> Self {
         Self {
+            size,
             build_speed: 3.,
             progress: 0.,
             deploy: false,
             building_queue: VecDeque::new(),
         }
     }
-    pub fn build(&mut self, structure: SpacecraftStructure) {
-        self.building_queue.push_back(structure);
+    pub fn can_build(&self, hull_class: HullClass) -> bool {
+        self.size >= hull_class.hangar_size()
+    }
+    pub fn build(&mut self, structure: SpacecraftStructure, build_time: f32) {
+        self.building_queue.push_back(HangarOrder {
+            structure,
+            build_time,
+        });
     }
     pub fn update(&mut self, dt: f32) -> Vec<HangarEffect> {
         if self.building_queue.is_empty() {
@@ -46,8 +69,8 @@ impl Hangar {
         if self.deploy && self.build_finished() {
             self.deploy = false;
             self.progress = 0.;
-            if let Some(structure) = self.building_queue.pop_front() {
-                result.push(HangarEffect::Deploy(structure));
+            if let Some(order) = self.building_queue.pop_front() {
+                result.push(HangarEffect::Deploy(order.structure));
             }
         }
         self.progress += self.build_speed * dt;
@@ -55,8 +78,8 @@ impl Hangar {
         result
     }
     pub fn build_finished(&self) -> bool {
-        if let Some(structure) = self.building_queue.front() {
-            self.progress >= structure.build_time()
+        if let Some(order) = self.building_queue.front() {
+            self.progress >= order.build_time
         } else {
             true
         }
@@ -65,7 +88,7 @@ impl Hangar {
         (self
             .building_queue
             .iter()
-            .map(|s| s.build_time())
+            .map(|order| order.build_time)
             .sum::<f32>()
             - self.progress)
             / self.build_speed
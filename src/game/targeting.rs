@@ -0,0 +1,214 @@
+use super::*;
+
+/// How a weapon chooses where to aim
+#[derive(Clone, Serialize, Deserialize, Debug, Copy, PartialEq, Eq, Default)]
+pub enum TargetingMode {
+    /// Aims along the rotation set by `ComponentCmd::SetRotation` and fires whenever active
+    #[default]
+    Manual,
+    /// Tracks the target set by `ComponentCmd::SetTarget`
+    Designated,
+    /// Tracks the nearest enemy spacecraft or star base
+    Nearest,
+    /// Tracks the enemy which approaches the fastest relative to its distance,
+    /// projectiles included
+    Threat,
+}
+
+/// Direction in which a projectile with the given speed has to be launched to hit a target
+/// at `relative_position` moving with `relative_velocity`, together with the time of the hit.
+/// None if the projectile can't catch up with the target.
+pub fn lead_intercept(
+    relative_position: Vec2,
+    relative_velocity: Vec2,
+    projectile_speed: f32,
+) -> Option<(Vec2, f32)> {
+    // |relative_position + relative_velocity * t| = projectile_speed * t
+    let a = relative_velocity.length_squared() - projectile_speed * projectile_speed;
+    let b = 2. * relative_position.dot(relative_velocity);
+    let c = relative_position.length_squared();
+    let time = if a.abs() < f32::EPSILON {
+        (b < 0.).then(|| -c / b)?
+    } else {
+        let discriminant = b * b - 4. * a * c;
+        if discriminant < 0. {
+            return None;
+        }
+        let sqrt = discriminant.sqrt();
+        [(-b - sqrt) / (2. * a), (-b + sqrt) / (2. * a)]
+            .into_iter()
+            .filter(|t| *t >= 0.)
+            .min_by(|a, b| a.partial_cmp(b).unwrap())?
+    };
+    let direction = (relative_position + relative_velocity * time).try_normalize()?;
+    Some((direction, time))
+}
+
+/// Firing solution of a weapon for one target
+struct Solution {
+    target: Option<GameObjectId>,
+    rotation: f32,
+    distance: f32,
+    threat: f32,
+}
+
+impl Game {
+    /// Computes the firing solutions of all auto-targeting weapons
+    pub(super) fn update_targeting(&mut self) {
+        let mut solutions = vec![];
+        for id in self.sorted_game_object_ids() {
+            let Some(GameObject::Spacecraft(spacecraft)) = self.game_objects.get(&id) else {
+                continue;
+            };
+            for (component_id, component) in &spacecraft.components {
+                let Component::Weapon(weapon) = component else {
+                    continue;
+                };
+                if weapon.targeting_mode == TargetingMode::Manual {
+                    continue;
+                }
+                let position = spacecraft
+                    .body
+                    .relative_to_world(weapon.body.position.as_vec2() - spacecraft.center_of_mass);
+                let base_rotation = spacecraft.body.rotation + weapon.body.orientation.to_radians();
+                let solve =
+                    |target: Option<GameObjectId>, target_position: Vec2, velocity: Vec2| {
+                        let relative_position = target_position - position;
+                        let relative_velocity = velocity - spacecraft.body.velocity;
+                        let (direction, time) = lead_intercept(
+                            relative_position,
+                            relative_velocity,
+                            weapon.projectile_speed,
+                        )?;
+                        let rotation = normalize_radians(direction.angle() - base_rotation);
+                        if rotation.abs() > weapon.max_angle()
+                            || weapon.projectile_speed * time > weapon.range
+                        {
+                            return None;
+                        }
+                        let distance = relative_position.length();
+                        let closing_speed = -relative_position.dot(relative_velocity) / distance;
+                        Some(Solution {
+                            target,
+                            rotation,
+                            distance,
+                            threat: (1. + closing_speed.max(0.)) / distance.max(1.),
+                        })
+                    };
+
+                let solution = match weapon.targeting_mode {
+                    TargetingMode::Manual => unreachable!(),
+                    TargetingMode::Designated => match weapon.target {
+                        Some(Target::GameObject(target_id)) => {
+                            self.game_objects.get(&target_id).and_then(|target| {
+                                let body = target.body();
+                                solve(Some(target_id), body.position, body.velocity)
+                            })
+                        }
+                        Some(Target::Point(point)) => solve(None, point, Vec2::ZERO),
+                        None => None,
+                    },
+                    TargetingMode::Nearest | TargetingMode::Threat => {
+                        let kinds = match weapon.targeting_mode {
+                            TargetingMode::Nearest => {
+                                vec![GameObjectKind::Spacecraft, GameObjectKind::StarBase]
+                            }
+                            _ => vec![
+                                GameObjectKind::Spacecraft,
+                                GameObjectKind::StarBase,
+                                GameObjectKind::Projectile,
+                            ],
+                        };
+                        let filter = QueryFilter::default()
+                            .with_owner(OwnerFilter::Except(spacecraft.owner))
+                            .with_kinds(&kinds);
+                        let candidates = self
+                            .query_circle(position, weapon.range, &filter)
+                            .into_iter()
+                            .filter_map(|target_id| {
+                                let body = self.game_objects[&target_id].body();
+                                solve(Some(target_id), body.position, body.velocity)
+                            });
+                        match weapon.targeting_mode {
+                            TargetingMode::Nearest => candidates
+                                .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap()),
+                            _ => candidates.max_by(|a, b| a.threat.partial_cmp(&b.threat).unwrap()),
+                        }
+                    }
+                };
+                solutions.push((id, *component_id, weapon.targeting_mode, solution));
+            }
+        }
+
+        for (id, component_id, targeting_mode, solution) in solutions {
+            let Some(GameObject::Spacecraft(spacecraft)) = self.game_objects.get_mut(&id) else {
+                continue;
+            };
+            let Some(Component::Weapon(weapon)) = spacecraft.components.get_mut(&component_id)
+            else {
+                continue;
+            };
+            weapon.firing_solution = solution.as_ref().map(|solution| solution.rotation);
+            if targeting_mode != TargetingMode::Designated {
+                weapon.target = solution
+                    .and_then(|solution| solution.target)
+                    .map(Target::GameObject);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    #[test]
+    fn weapon_leads_crossing_target() {
+        let mut game = Game::with_seed(0);
+        for (owner, position, velocity) in [
+            (0, Vec2::ZERO, Vec2::ZERO),
+            (1, vec2(60., -20.), vec2(0., 4.)),
+        ] {
+            let spacecraft = SpacecraftBuilder::new()
+                .with(ComponentType::STEEL_BLOCK, ivec2(0, 1), Orientation::Up)
+                .with(ComponentType::KINETIC_WEAPON, ivec2(0, 1), Orientation::Up)
+                .at(position, velocity, 0.)
+                .owner(owner)
+                .build();
+            game.handle_game_object_effect(GameObjectEffect::SpawnSpacecraft(spacecraft));
+        }
+        let ids = game.sorted_game_object_ids();
+        let owned_by = |owner| {
+            *ids.iter()
+                .find(|id| game.game_objects[id].owner() == Some(owner))
+                .unwrap()
+        };
+        let (shooter_id, target_id) = (owned_by(0), owned_by(1));
+        let Some(GameObject::Spacecraft(shooter)) = game.game_objects.get_mut(&shooter_id) else {
+            unreachable!()
+        };
+        // top components are numbered from 256
+        shooter.execute_component_cmd(258, ComponentCmd::SetTargetingMode(TargetingMode::Nearest));
+        shooter.execute_component_cmd(258, ComponentCmd::SetActive(true));
+
+        let bullet = (0..200)
+            .find_map(|_| {
+                game.update(0.05);
+                game.events.iter().find_map(|event| match event {
+                    GameEvent::ProjectileLaunched(projectile) => Some(projectile.clone()),
+                    _ => None,
+                })
+            })
+            .unwrap();
+        let target = game.game_objects[&target_id].body();
+        let closest = (0..400)
+            .map(|step| {
+                let t = step as f32 * 0.005;
+                (bullet.body.position + bullet.body.velocity * t)
+                    .distance(target.position + target.velocity * t)
+            })
+            .fold(f32::MAX, f32::min);
+        assert!(closest < 0.5, "missed by {}", closest);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::prelude::*;
+
+use super::spacecraft::{components_bounds, components_center_of_mass, components_inertia};
+
+/// Drifting remains of a spacecraft, made of components which lost the connection to the
+/// central component. Wrecks keep colliding and can be salvaged for materials by shooting them.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct Wreck {
+    pub body: GameObjectBody,
+    pub components: BTreeMap<ComponentId, Component>,
+    /// In the grid coordinates of the spacecraft the wreck came from
+    pub center_of_mass: Vec2,
+    pub mass: f32,
+    pub inertia: f32,
+    health: f32,
+}
+
+impl Wreck {
+    /// Detaches `components` from a spacecraft, the wreck keeps the velocity of its position
+    /// on the spacecraft and the angular velocity of the spacecraft
+    pub fn new(
+        components: BTreeMap<ComponentId, Component>,
+        parent: &GameObjectBody,
+        parent_center_of_mass: Vec2,
+    ) -> Self {
+        let center_of_mass = components_center_of_mass(&components);
+        let position = parent.relative_to_world(center_of_mass - parent_center_of_mass);
+        let mut body = GameObjectBody::new(
+            position,
+            parent.point_velocity(position),
+            parent.rotation,
+            parent.cur_time,
+            vec![],
+        );
+        body.angular_velocity = parent.angular_velocity;
+
+        let mut wreck = Self {
+            body,
+            components,
+            center_of_mass,
+            mass: 0.,
+            inertia: 0.,
+            health: 0.,
+        };
+        wreck.reconstruct();
+        wreck
+    }
+    pub fn update(&mut self, _time: f32) -> Vec<GameObjectEffect> {
+        self.reconstruct();
+        vec![]
+    }
+    fn reconstruct(&mut self) {
+        self.components.retain(|_, x| x.health() > 0.);
+        if self.components.is_empty() {
+            self.health = 0.;
+            self.body.bounds.clear();
+            return;
+        }
+
+        let new_center_of_mass = components_center_of_mass(&self.components);
+        self.body.position +=
+            (new_center_of_mass - self.center_of_mass).rotate_rad(self.body.rotation);
+        self.center_of_mass = new_center_of_mass;
+
+        self.body.bounds = components_bounds(&self.components, self.center_of_mass);
+        self.mass = self.components.values().map(|x| x.mass()).sum();
+        self.inertia = components_inertia(&self.components, self.center_of_mass);
+        self.health = self.components.values().map(|x| x.health()).sum();
+    }
+    pub fn world_to_grid(&self, world_pos: Vec2) -> Vec2 {
+        self.body.world_to_relative(world_pos) + self.center_of_mass
+    }
+}
+
+impl Wreck {
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+    pub fn health(&self) -> f32 {
+        self.health
+    }
+    pub fn destroyed(&self) -> bool {
+        self.components.is_empty()
+    }
+    pub fn owner(&self) -> Option<PlayerId> {
+        None
+    }
+    /// Salvaging, the components closest to the hit give out materials for the damage they take
+    pub fn apply_damage(&mut self, damage: f32, position: Vec2) -> Vec<(Material, f32)> {
+        let hit = self.world_to_grid(position);
+        let mut components = self.components.values_mut().collect::<Vec<_>>();
+        components.sort_by(|a, b| {
+            let a = a.body().centered_position().distance(hit);
+            let b = b.body().centered_position().distance(hit);
+            a.partial_cmp(&b).unwrap()
+        });
+
+        let mut result = vec![];
+        let mut damage = damage;
+        for component in components {
+            let component_damage = damage.min(component.health()).max(0.);
+            component.apply_damage(component_damage);
+            damage -= component_damage;
+            self.health -= component_damage;
+            let body = component.body();
+            result.extend(body.materials.iter().map(|(material, amount)| {
+                (*material, amount * component_damage / body.max_health)
+            }));
+            if damage <= 0. {
+                break;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::spacecraft::SpacecraftBuilder;
+
+    #[test]
+    fn cut_off_wing_becomes_wreck() {
+        // central component with a two block wing on the left, ids 1 and 2
+        let mut spacecraft = SpacecraftBuilder::new()
+            .with(ComponentType::STEEL_BLOCK, ivec2(-1, 0), Orientation::Up)
+            .with(ComponentType::STEEL_BLOCK, ivec2(-2, 0), Orientation::Up)
+            .at(vec2(10., 5.), vec2(3., -1.), 0.7)
+            .build();
+        spacecraft.body.angular_velocity = 0.5;
+        let parent = spacecraft.body.clone();
+        let tip_mass = spacecraft.components[&2].mass();
+        let central_mass = spacecraft.components[&0].mass();
+
+        let inner = spacecraft.components.get_mut(&1).unwrap();
+        inner.apply_damage(inner.body().max_health);
+        let mut effects = spacecraft.reconstruct();
+
+        assert_eq!(effects.len(), 1);
+        let Some(GameObjectEffect::SpawnWreck(wreck)) = effects.pop() else {
+            unreachable!()
+        };
+        assert_eq!(wreck.components.keys().collect::<Vec<_>>(), vec![&2]);
+        assert_eq!(spacecraft.components.keys().collect::<Vec<_>>(), vec![&0]);
+        assert_eq!(wreck.mass(), tip_mass);
+        assert_eq!(spacecraft.mass(), central_mass);
+
+        // moves like the point of the spacecraft it was cut from
+        let arm = wreck.body.position - parent.position;
+        let expected = parent.velocity + arm.perp() * parent.angular_velocity;
+        assert!(wreck.body.velocity.distance(expected) < 1e-4);
+        assert_eq!(wreck.body.angular_velocity, parent.angular_velocity);
+        assert!(arm.length() > 0.5);
+    }
+}
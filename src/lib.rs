@@ -5,6 +5,7 @@ pub mod game;
 pub mod network;
 
 pub const GAME_BUFFER_SIZE: usize = 2 << 15;
+/// Cells across the largest construction grid, the one of cruisers
 pub const CONSTRUCTION_SIZE: usize = 30;
 
 pub mod prelude {
@@ -44,10 +45,11 @@ pub mod prelude {
     pub use network::{ClientRequest, NetworkError, ServerResponse};
 
     pub use game::{
-        collision_detection::convex_hull, Asteroid, Component, ComponentCmd, ComponentId,
-        ComponentPlaceholder, ComponentType, Game, GameCmd, GameEvent, GameObject, GameObjectBody,
-        GameObjectEffect, GameObjectId, Material, Orientation, Player, PlayerId, Projectile,
-        ProjectileType, Spacecraft, SpacecraftStructure, StarBase, User,
+        collision_detection::convex_hull, Asteroid, Component, ComponentCatalogue, ComponentCmd,
+        ComponentId, ComponentPlaceholder, ComponentType, FlightGoal, Game, GameCmd, GameDelta,
+        GameEvent, GameObject, GameObjectBody, GameObjectEffect, GameObjectId, HullClass, Material,
+        Orientation, Player, PlayerId, Projectile, ProjectileType, Spacecraft, SpacecraftStructure,
+        StarBase, StateChecksum, Target, TargetingMode, User, Wreck,
     };
     pub use glam::{ivec2, uvec2, vec2, IVec2, UVec2, Vec2};
 
@@ -106,7 +108,7 @@ pub mod prelude {
             Vec2::from_angle(rng.gen::<f32>() * 2.0 * PI)
         }
         fn random_unit_circle_seed(rng: &mut ChaChaRng) -> Vec2 {
-            Vec2::random_direction_seed(rng) * rand::random::<f32>().sqrt()
+            Vec2::random_direction_seed(rng) * rng.gen::<f32>().sqrt()
         }
         fn random_direction() -> Vec2 {
             Vec2::from_angle(rand::random::<f32>() * 2.0 * PI)
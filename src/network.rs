@@ -7,11 +7,13 @@ pub type AccessToken = String;
 pub enum ServerResponse {
     SyncGameCmds(Vec<(User, GameCmd)>),
     SyncFullGame(Game),
+    SyncGameDelta(GameDelta),
     SetUser(User),
     SlowDown,
     Success,
     Error,
     SyncClock(time::Duration),
+    StateChecksum(StateChecksum),
     WrongAuthToken,
     NotAuthorized,
     ServerFull
@@ -23,8 +25,10 @@ pub enum ClientRequest {
     ExecuteGameCmds(Vec<GameCmd>), // "{\"ExecGameCmds\":[{\"SpawnAsteroid\":[193.66406,126.02344]}, {\"ExecuteComponentCmd\":[0, 10, 2, {"SetActive": true}}]
     Join(PlayerId, AccessToken),
     FullGameSync,
+    GameDeltaSync(usize), // frame of the last state received by the client
     GameCmdsSync,
     SyncClock,
+    StateChecksum(usize), // frame
 }
 
 #[derive(Debug)]